@@ -0,0 +1,280 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use sha2::{Digest, Sha256};
+
+/// The directory within the user's home directory in which artifacts are cached by default.
+pub(crate) const DEFAULT_ARTIFACT_FOLDER: &str =
+    ".nargo/backends/acvm-backend-barretenberg/artifacts";
+
+/// The default limit on the total size of the artifact cache, in bytes.
+pub(crate) const DEFAULT_MAX_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Name of the file whose modification time records when an entry was last used.
+const LAST_USED_FILE: &str = "last_used";
+
+/// An artifact produced by barretenberg for a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Artifact {
+    GateCount,
+    VerificationKey,
+    Contract,
+}
+
+impl Artifact {
+    fn file_name(self) -> &'static str {
+        match self {
+            Artifact::GateCount => "gates",
+            Artifact::VerificationKey => "vk",
+            Artifact::Contract => "contract",
+        }
+    }
+}
+
+/// An on-disk cache of the artifacts which barretenberg produces for each circuit.
+///
+/// Entries are addressed by a hash of the circuit's bytecode, the digest of the barretenberg binary and whether
+/// the circuit is to be used recursively, so the cache never needs to be invalidated. Instead, the least recently
+/// used entries are evicted once the cache grows beyond its size limit.
+///
+/// The cache is best-effort: failing to read from or write to it never causes an operation to fail.
+#[derive(Debug, Clone)]
+pub(crate) struct ArtifactCache {
+    directory: PathBuf,
+    max_size: u64,
+    /// The total size of the cache, which is measured when the first artifact is stored and then kept up to date.
+    ///
+    /// Artifacts stored by other processes are only accounted for when the cache is next measured.
+    size: Arc<Mutex<Option<u64>>>,
+}
+
+/// The cached artifacts for a single circuit.
+///
+/// Each entry holds a handle to the cache, so that it can be moved between threads independently of the backend.
+#[derive(Debug)]
+pub(crate) struct CacheEntry {
+    cache: ArtifactCache,
+    directory: PathBuf,
+}
+
+impl ArtifactCache {
+    pub(crate) fn new(directory: PathBuf, max_size: u64) -> ArtifactCache {
+        ArtifactCache {
+            directory,
+            max_size,
+            size: Arc::default(),
+        }
+    }
+
+    /// Returns the entry holding the artifacts for the circuit with the serialized `bytecode`
+    /// which are produced by the barretenberg binary with the digest `binary_sha256`.
+    pub(crate) fn entry(
+        &self,
+        bytecode: &[u8],
+        binary_sha256: &str,
+        is_recursive: bool,
+    ) -> CacheEntry {
+        let mut hasher = Sha256::new();
+        hasher.update(bytecode);
+        hasher.update(binary_sha256.as_bytes());
+        hasher.update([u8::from(is_recursive)]);
+        let key: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        CacheEntry {
+            cache: self.clone(),
+            directory: self.directory.join(key),
+        }
+    }
+
+    /// Accounts for an artifact of `previous_size` bytes being replaced by one of `new_size` bytes,
+    /// evicting other entries if the cache has grown too large.
+    fn grow(&self, previous_size: u64, new_size: u64) {
+        let mut size = self.size.lock().unwrap_or_else(|error| error.into_inner());
+        let total_size = match *size {
+            Some(total_size) => total_size.saturating_sub(previous_size) + new_size,
+            None => self.measure(),
+        };
+        *size = Some(if total_size > self.max_size {
+            self.evict()
+        } else {
+            total_size
+        });
+    }
+
+    /// Returns the total size of every entry in the cache.
+    fn measure(&self) -> u64 {
+        self.entries().iter().map(|path| directory_size(path)).sum()
+    }
+
+    /// Returns the directory of every entry in the cache.
+    fn entries(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
+    /// Removes the least recently used entries until the cache fits within its size limit,
+    /// returning the size of the remaining entries.
+    fn evict(&self) -> u64 {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = self
+            .entries()
+            .into_iter()
+            .map(|path| {
+                let last_used = std::fs::metadata(path.join(LAST_USED_FILE))
+                    .and_then(|metadata| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (last_used, directory_size(&path), path)
+            })
+            .collect();
+
+        let mut total_size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort();
+        for (_, size, path) in entries {
+            if total_size <= self.max_size {
+                break;
+            }
+            if std::fs::remove_dir_all(&path).is_ok() {
+                total_size -= size;
+            }
+        }
+        total_size
+    }
+}
+
+impl CacheEntry {
+    /// Returns the cached `artifact`, if there is one.
+    pub(crate) fn get(&self, artifact: Artifact) -> Option<Vec<u8>> {
+        let contents = std::fs::read(self.directory.join(artifact.file_name())).ok()?;
+        self.touch();
+        Some(contents)
+    }
+
+    /// Stores `contents` as the cached `artifact`, evicting other entries if the cache has grown too large.
+    pub(crate) fn put(&self, artifact: Artifact, contents: &[u8]) {
+        if let Ok(previous_size) = self.write(artifact, contents) {
+            self.touch();
+            self.cache.grow(previous_size, contents.len() as u64);
+        }
+    }
+
+    /// Writes `contents` as the cached `artifact`, returning the size of the artifact which it replaced, if any.
+    fn write(&self, artifact: Artifact, contents: &[u8]) -> std::io::Result<u64> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(artifact.file_name());
+        let previous_size = std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+
+        // Write to a temporary file first so that other processes never read a partially written artifact.
+        let mut temp_file = tempfile::NamedTempFile::new_in(&self.directory)?;
+        temp_file.write_all(contents)?;
+        temp_file.persist(path).map_err(|error| error.error)?;
+        Ok(previous_size)
+    }
+
+    /// Records that this entry has just been used, so that it is evicted last.
+    fn touch(&self) {
+        let _ = std::fs::write(self.directory.join(LAST_USED_FILE), []);
+    }
+}
+
+fn directory_size(directory: &Path) -> u64 {
+    std::fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[test]
+fn artifacts_are_cached_per_circuit() {
+    use tempfile::tempdir;
+
+    const BINARY: &str = "00a02f9f94908a63555f0f26463029dafca16d437cb97b08282924b7b3d925dc";
+    const OTHER_BINARY: &str = "a0365246cbe12542c9368060b3ed29bc09d6d470303185a23d5ffd0dabcea90d";
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let cache = ArtifactCache::new(temp_directory.path().to_path_buf(), DEFAULT_MAX_SIZE);
+
+    let entry = cache.entry(b"circuit", BINARY, false);
+    assert_eq!(entry.get(Artifact::VerificationKey), None);
+    entry.put(Artifact::VerificationKey, b"vk");
+    assert_eq!(entry.get(Artifact::VerificationKey), Some(b"vk".to_vec()));
+
+    // The same circuit used recursively or with another binary and other circuits have separate entries.
+    assert_eq!(
+        cache
+            .entry(b"circuit", BINARY, true)
+            .get(Artifact::VerificationKey),
+        None
+    );
+    assert_eq!(
+        cache
+            .entry(b"circuit", OTHER_BINARY, false)
+            .get(Artifact::VerificationKey),
+        None
+    );
+    assert_eq!(
+        cache
+            .entry(b"other", BINARY, false)
+            .get(Artifact::VerificationKey),
+        None
+    );
+    assert_eq!(
+        cache
+            .entry(b"circuit", BINARY, false)
+            .get(Artifact::VerificationKey),
+        Some(b"vk".to_vec())
+    );
+
+    drop(temp_directory);
+}
+
+#[test]
+fn least_recently_used_artifacts_are_evicted() {
+    use tempfile::tempdir;
+
+    const BINARY: &str = "00a02f9f94908a63555f0f26463029dafca16d437cb97b08282924b7b3d925dc";
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let cache = ArtifactCache::new(temp_directory.path().to_path_buf(), 2048);
+
+    let first = cache.entry(b"first", BINARY, false);
+    first.put(Artifact::VerificationKey, &[0; 1000]);
+    let second = cache.entry(b"second", BINARY, false);
+    second.put(Artifact::VerificationKey, &[0; 1000]);
+
+    // Using the first entry again should make the second entry the one to be evicted.
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    assert!(first.get(Artifact::VerificationKey).is_some());
+
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let third = cache.entry(b"third", BINARY, false);
+    third.put(Artifact::VerificationKey, &[0; 1000]);
+
+    assert!(first.get(Artifact::VerificationKey).is_some());
+    assert!(second.get(Artifact::VerificationKey).is_none());
+    assert!(third.get(Artifact::VerificationKey).is_some());
+
+    // The size of the remaining entries is kept track of without measuring the cache again.
+    assert_eq!(*cache.size.lock().unwrap(), Some(2000));
+    third.put(Artifact::VerificationKey, &[0; 500]);
+    assert_eq!(*cache.size.lock().unwrap(), Some(1500));
+
+    drop(temp_directory);
+}
@@ -0,0 +1,267 @@
+use std::ops::ControlFlow;
+
+use acvm::acir::{circuit::Circuit, native_types::WitnessMap};
+
+use crate::{
+    artifacts::Artifact,
+    bb,
+    proof_system::{cached_gate_count, remove_public_inputs, serialize_circuit},
+    smart_contract::{finish_contract, ContractSource},
+    trace, BackendError, Barretenberg, Error,
+};
+
+/// Asynchronous counterparts of the [`acvm::ProofSystemCompiler`] and [`acvm::SmartContract`] methods,
+/// which are available with the `async` feature.
+///
+/// These await the barretenberg binary rather than blocking the calling thread, which makes them suitable
+/// for use within a tokio runtime. If the returned future is dropped before it completes then the binary is killed.
+///
+/// Commands are always run in a process of their own, even if worker mode is enabled. Preparing the inputs to
+/// barretenberg, such as downloading the binary or regenerating a verification key, is done on tokio's pool of
+/// blocking threads, as is storing the results in the artifact cache.
+impl Barretenberg {
+    /// Returns the number of gates in `circuit` without blocking.
+    pub async fn get_exact_circuit_size_async(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<u32, BackendError> {
+        trace::in_circuit(circuit, async {
+            let backend = self.clone();
+            let owned_circuit = circuit.clone();
+            let prepared = blocking(move || {
+                let _circuit_span = trace::enter_circuit(&owned_circuit);
+                let serialized_circuit = serialize_circuit(&owned_circuit)?;
+                let cached_artifacts = backend.cached_artifacts(&serialized_circuit, false);
+                if let Some(gate_count) = cached_gate_count(cached_artifacts.as_ref()) {
+                    return Ok(ControlFlow::Break(gate_count));
+                }
+                bb::ensure_binary(&backend.config)?;
+                let (command, temp_files) = backend.prepare_gates(serialized_circuit)?;
+                Ok::<_, Error>(ControlFlow::Continue((
+                    command,
+                    temp_files,
+                    cached_artifacts,
+                )))
+            })
+            .await?;
+            let (command, _temp_files, cached_artifacts) = match prepared {
+                ControlFlow::Break(gate_count) => return Ok(gate_count),
+                ControlFlow::Continue(prepared) => prepared,
+            };
+
+            let number_of_gates_needed = command.run_async(&self.config).await?;
+
+            if let Some(artifacts) = cached_artifacts {
+                blocking(move || {
+                    artifacts.put(Artifact::GateCount, &number_of_gates_needed.to_le_bytes());
+                })
+                .await;
+            }
+            Ok(number_of_gates_needed)
+        })
+        .await
+    }
+
+    /// Creates a proof that `witness_values` satisfy `circuit` without blocking.
+    pub async fn prove_with_pk_async(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        witness_values: WitnessMap,
+        proving_key: &[u8],
+        is_recursive: bool,
+    ) -> Result<Vec<u8>, BackendError> {
+        trace::in_circuit(circuit, async {
+            let backend = self.clone();
+            let common_reference_string = common_reference_string.to_vec();
+            let owned_circuit = circuit.clone();
+            let proving_key = proving_key.to_vec();
+            let (command, temp_files) = blocking(move || {
+                let _circuit_span = trace::enter_circuit(&owned_circuit);
+                bb::ensure_binary(&backend.config)?;
+                backend.prepare_proof(
+                    &common_reference_string,
+                    &owned_circuit,
+                    witness_values,
+                    &proving_key,
+                    is_recursive,
+                )
+            })
+            .await?;
+            let proof_with_public_inputs = command.run_async(&self.config).await;
+            // Dropping the temporary files wipes the witness from disk.
+            blocking(move || drop(temp_files)).await;
+
+            // As when proving synchronously, barretenberg prepends the public inputs to the proof.
+            let proof =
+                remove_public_inputs(circuit.public_inputs().0.len(), &proof_with_public_inputs?)?;
+            Ok(proof)
+        })
+        .await
+    }
+
+    /// Verifies `proof` against `verification_key` without blocking.
+    pub async fn verify_with_vk_async(
+        &self,
+        common_reference_string: &[u8],
+        proof: &[u8],
+        public_inputs: WitnessMap,
+        circuit: &Circuit,
+        verification_key: &[u8],
+        is_recursive: bool,
+    ) -> Result<bool, BackendError> {
+        trace::in_circuit(circuit, async {
+            let backend = self.clone();
+            let common_reference_string = common_reference_string.to_vec();
+            let proof = proof.to_vec();
+            let owned_circuit = circuit.clone();
+            let verification_key = verification_key.to_vec();
+            let (command, _temp_files) = blocking(move || {
+                let _circuit_span = trace::enter_circuit(&owned_circuit);
+                bb::ensure_binary(&backend.config)?;
+                backend.prepare_verification(
+                    &common_reference_string,
+                    &proof,
+                    public_inputs,
+                    &owned_circuit,
+                    &verification_key,
+                    is_recursive,
+                )
+            })
+            .await?;
+            Ok(command.run_async(&self.config).await?)
+        })
+        .await
+    }
+
+    /// Generates a Solidity verifier for `verification_key` without blocking.
+    pub async fn eth_contract_from_vk_async(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        verification_key: &[u8],
+    ) -> Result<String, BackendError> {
+        trace::in_circuit(circuit, async {
+            let backend = self.clone();
+            let common_reference_string = common_reference_string.to_vec();
+            let owned_circuit = circuit.clone();
+            let verification_key = verification_key.to_vec();
+            let source = blocking(move || {
+                let _circuit_span = trace::enter_circuit(&owned_circuit);
+                bb::ensure_binary(&backend.config)?;
+                backend.prepare_contract(
+                    &common_reference_string,
+                    &owned_circuit,
+                    &verification_key,
+                )
+            })
+            .await?;
+
+            match source {
+                ContractSource::Cached(contract) => Ok(contract),
+                ContractSource::Generate {
+                    command,
+                    cached_artifacts,
+                    temp_files,
+                } => {
+                    let verification_key_library_bytes = command.run_async(&self.config).await?;
+                    Ok(blocking(move || {
+                        drop(temp_files);
+                        finish_contract(verification_key_library_bytes, cached_artifacts)
+                    })
+                    .await?)
+                }
+            }
+        })
+        .await
+    }
+}
+
+/// Runs `f` on tokio's pool of threads on which blocking is acceptable, such as to write files or run barretenberg.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        // The task is only cancelled when the runtime shuts down, which drops this future rather than resuming it,
+        // so the task must have panicked.
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+#[test]
+fn async_methods_return_send_futures() {
+    use acvm::ProofSystemCompiler;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    let bb = Barretenberg::builder().build();
+    let circuit = Circuit::default();
+    assert_send(&bb.get_exact_circuit_size_async(&circuit));
+    assert_send(&bb.prove_with_pk_async(&[], &circuit, WitnessMap::new(), &[], false));
+    assert_send(&bb.verify_with_vk_async(&[], &[], WitnessMap::new(), &circuit, &[], false));
+    assert_send(&bb.eth_contract_from_vk_async(&[], &circuit, &[]));
+
+    // The async methods should agree with their blocking counterparts.
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    crate::bb::write_mock_binary(
+        &binary_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\nprintf '\\007\\000\\000\\000\\000\\000\\000\\000'\n",
+    );
+    let bb = Barretenberg::builder()
+        .binary_path(&binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .artifact_cache(false)
+        .build();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let gate_count = runtime
+        .block_on(bb.get_exact_circuit_size_async(&circuit))
+        .unwrap();
+    assert_eq!(gate_count, bb.get_exact_circuit_size(&circuit).unwrap());
+    assert_eq!(gate_count, 7);
+
+    drop(temp_directory);
+}
+
+#[test]
+fn preparation_does_not_block_the_runtime() {
+    use std::time::{Duration, Instant};
+
+    // Probing the version of this binary takes long enough to stall the runtime if it were done on its thread.
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    crate::bb::write_mock_binary(
+        &binary_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && sleep 1 && echo 0.4.6 && exit 0\nprintf '\\007\\000\\000\\000\\000\\000\\000\\000'\n",
+    );
+    let bb = Barretenberg::builder()
+        .binary_path(&binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .artifact_cache(false)
+        .build();
+    let circuit = Circuit::default();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let ticker = async {
+        for _ in 0..5 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Instant::now()
+    };
+    let size = async {
+        let gate_count = bb.get_exact_circuit_size_async(&circuit).await;
+        (gate_count, Instant::now())
+    };
+    let (ticked_at, (gate_count, sized_at)) =
+        runtime.block_on(async { tokio::join!(ticker, size) });
+    assert_eq!(gate_count.unwrap(), 7);
+    assert!(ticked_at < sized_at);
+
+    drop(temp_directory);
+}
@@ -0,0 +1,203 @@
+use std::{
+    process::{Command, Output, Stdio},
+    time::Instant,
+};
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+
+use super::{
+    cancellation, finish, predicate_result, successful_output, CliShimConfig, CliShimError,
+    Deadline, OutputLines,
+};
+
+/// Executes `command` to completion without blocking the calling thread, returning its output regardless of the exit status.
+///
+/// This behaves as `execute`, except that the barretenberg binary is killed if the returned future is dropped
+/// before it completes. Commands are always run in a process of their own, even if worker mode is enabled.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "bb",
+        skip_all,
+        err,
+        fields(
+            operation = name,
+            exit_status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        )
+    )
+)]
+pub(super) async fn execute_async(
+    config: &CliShimConfig,
+    name: &'static str,
+    command: Command,
+    stdin: Option<&[u8]>,
+) -> Result<Output, CliShimError> {
+    let deadline = Deadline::start(config, name)?;
+    let started = Instant::now();
+
+    let spawn_error = |source| CliShimError::Spawn {
+        command: name,
+        source,
+    };
+
+    let mut child = tokio::process::Command::from(command)
+        .kill_on_drop(true)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+    let process_group = child.id().map(ProcessGroup);
+
+    // The input is written while waiting, as the binary may not read it all before writing its output.
+    let child_stdin = child.stdin.take();
+    let write_stdin = async move {
+        if let (Some(mut child_stdin), Some(stdin)) = (child_stdin, stdin) {
+            // The binary may exit without reading its input, which is reported through its exit status.
+            let _ = child_stdin.write_all(stdin).await;
+        }
+    };
+    let stdout = read_pipe(
+        child.stdout.take(),
+        OutputLines::new(config, name, "stdout", started),
+    );
+    let stderr = read_pipe(
+        child.stderr.take(),
+        OutputLines::new(config, name, "stderr", started),
+    );
+    let run = async move {
+        let ((), stdout, stderr, status) = tokio::join!(write_stdin, stdout, stderr, child.wait());
+        Ok(Output {
+            status: status.map_err(spawn_error)?,
+            stdout,
+            stderr,
+        })
+    };
+
+    // Returning early drops `run` and `process_group`, which kills the binary and any processes it has started.
+    let output = if deadline.is_unbounded() {
+        run.await
+    } else {
+        tokio::pin!(run);
+        loop {
+            let poll_interval = deadline.check()?;
+            tokio::select! {
+                output = &mut run => break output,
+                () = tokio::time::sleep(poll_interval) => {}
+            }
+        }
+    };
+
+    // The binary has been waited on, so its process ID may since have been reused.
+    std::mem::forget(process_group);
+    finish(config, name, started, output?, None)
+}
+
+/// Reads `pipe` to completion, handling each line as it is read.
+async fn read_pipe(pipe: Option<impl AsyncRead + Unpin>, mut lines: OutputLines) -> Vec<u8> {
+    let mut contents = Vec::new();
+    let Some(pipe) = pipe else {
+        return contents;
+    };
+
+    let mut pipe = BufReader::new(pipe);
+    loop {
+        let start = contents.len();
+        match pipe.read_until(b'\n', &mut contents).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => lines.line(&contents[start..]),
+        }
+    }
+    contents
+}
+
+/// The process group of a running barretenberg binary, which is killed when dropped.
+struct ProcessGroup(u32);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        cancellation::kill_process_group(self.0);
+    }
+}
+
+/// Executes `command` without blocking, returning its output if the barretenberg binary exited successfully.
+pub(super) async fn run_command_async(
+    config: &CliShimConfig,
+    name: &'static str,
+    command: Command,
+    stdin: Option<&[u8]>,
+) -> Result<Output, CliShimError> {
+    successful_output(name, execute_async(config, name, command, stdin).await?)
+}
+
+/// Executes a command which reports a boolean result through its exit code without blocking.
+pub(super) async fn run_predicate_command_async(
+    config: &CliShimConfig,
+    name: &'static str,
+    command: Command,
+    stdin: Option<&[u8]>,
+) -> Result<bool, CliShimError> {
+    predicate_result(name, execute_async(config, name, command, stdin).await?)
+}
+
+#[test]
+fn dropped_commands_are_killed() {
+    use std::time::Duration;
+
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    let marker_path = temp_directory.path().join("finished");
+    super::write_mock_binary(
+        &binary_path,
+        &format!("#!/bin/sh\ncat\nsleep 1\ntouch {}\n", marker_path.display()),
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let config = CliShimConfig::default();
+
+        let output = run_command_async(&config, "prove", Command::new("cat"), Some(b"witness"))
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, b"witness");
+
+        // Abandoning the command should kill the binary before it gets the chance to finish.
+        let command = execute_async(&config, "prove", Command::new(&binary_path), Some(b""));
+        assert!(tokio::time::timeout(Duration::from_millis(200), command)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker_path.exists());
+
+        // Cancelling the command should also kill the binary.
+        let cancellation = super::CancellationHandle::new();
+        let config = CliShimConfig {
+            cancellation: Some(cancellation.clone()),
+            ..CliShimConfig::default()
+        };
+        let command = execute_async(&config, "prove", Command::new(&binary_path), Some(b""));
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            cancellation.cancel();
+        };
+        let (result, ()) = tokio::join!(command, cancel);
+        assert!(matches!(
+            result,
+            Err(CliShimError::Cancelled { command: "prove" })
+        ));
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker_path.exists());
+    });
+
+    drop(temp_directory);
+}
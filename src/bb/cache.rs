@@ -0,0 +1,248 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{install, CliShimConfig, CliShimError, BINARY_NAME, DEST_FOLDER};
+
+/// Name of the file within each version's directory which records where the binary came from.
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Records the provenance of a barretenberg binary stored in the binary cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryManifest {
+    /// The barretenberg release which the binary belongs to.
+    pub version: String,
+    /// The URL or local path from which the binary was installed.
+    pub source: String,
+    /// The SHA-256 digest of the installed `bb` executable.
+    pub sha256: String,
+}
+
+/// A barretenberg binary stored in the binary cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedBinary {
+    /// The barretenberg release which the binary belongs to.
+    pub version: String,
+    /// The path to the `bb` executable.
+    pub path: PathBuf,
+    /// The manifest describing the binary, if one was recorded when it was installed.
+    pub manifest: Option<BinaryManifest>,
+}
+
+/// Returns the directory under which the binaries for each barretenberg release are cached.
+///
+/// This defaults to a directory within the user's home directory unless `config` sets one.
+fn cache_root(config: &CliShimConfig) -> Result<PathBuf, CliShimError> {
+    if let Some(cache_dir) = &config.cache_dir {
+        return Ok(cache_dir.clone());
+    }
+
+    dirs::home_dir()
+        .map(|home| home.join(DEST_FOLDER))
+        .ok_or_else(|| CliShimError::BinaryNotFound(PathBuf::from(DEST_FOLDER)))
+}
+
+/// Returns the directory in which the binary for `version` is cached.
+fn version_directory(config: &CliShimConfig, version: &str) -> Result<PathBuf, CliShimError> {
+    // Versions are used as directory names so must not be able to escape the cache.
+    let is_valid_version = !version.is_empty()
+        && !version.starts_with('.')
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+    if !is_valid_version {
+        return Err(CliShimError::InvalidVersion(version.to_string()));
+    }
+
+    Ok(cache_root(config)?.join(version))
+}
+
+/// Returns the path at which the binary for `version` is cached.
+pub(super) fn binary_path(config: &CliShimConfig, version: &str) -> Result<PathBuf, CliShimError> {
+    Ok(version_directory(config, version)?.join(BINARY_NAME))
+}
+
+/// Returns the path to the cached binary for `version`, downloading it if it is not already cached.
+///
+/// A downloaded release archive must match `archive_sha256` if it is provided.
+pub(super) fn install_version(
+    config: &CliShimConfig,
+    version: &str,
+    archive_sha256: Option<&str>,
+) -> Result<PathBuf, CliShimError> {
+    let binary_path = binary_path(config, version)?;
+    if !binary_path.exists() {
+        install::with_install_lock(&binary_path, || {
+            // Another process may have installed the binary while we were waiting for the lock.
+            if !binary_path.exists() {
+                let manifest =
+                    install::download_bb_binary(config, version, archive_sha256, &binary_path)?;
+                write_manifest(&binary_path, &manifest)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(binary_path)
+}
+
+/// Installs the binary at `source` into the cache for `version`, returning the path to the installed binary.
+pub(super) fn install_from_path(
+    config: &CliShimConfig,
+    version: &str,
+    source: &Path,
+) -> Result<PathBuf, CliShimError> {
+    let binary_path = binary_path(config, version)?;
+    install::with_install_lock(&binary_path, || {
+        let manifest = install::install_from_path(config, version, source, &binary_path)?;
+        write_manifest(&binary_path, &manifest)
+    })?;
+    Ok(binary_path)
+}
+
+/// Records `manifest` alongside the binary at `binary_path`.
+pub(super) fn write_manifest(
+    binary_path: &Path,
+    manifest: &BinaryManifest,
+) -> Result<(), CliShimError> {
+    let manifest_path = binary_path.with_file_name(MANIFEST_NAME);
+    serde_json::to_vec_pretty(manifest)
+        .map_err(std::io::Error::from)
+        .and_then(|contents| std::fs::write(&manifest_path, contents))
+        .map_err(|source| CliShimError::Install {
+            path: manifest_path,
+            source,
+        })
+}
+
+/// Reads the manifest recorded alongside the binary at `binary_path`, if there is one.
+pub(super) fn read_manifest(binary_path: &Path) -> Option<BinaryManifest> {
+    let contents = std::fs::read(binary_path.with_file_name(MANIFEST_NAME)).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+/// Returns all barretenberg binaries in the cache, ordered by version.
+pub(super) fn list_cached_versions(
+    config: &CliShimConfig,
+) -> Result<Vec<CachedBinary>, CliShimError> {
+    list_in(&cache_root(config)?)
+}
+
+fn list_in(cache_root: &Path) -> Result<Vec<CachedBinary>, CliShimError> {
+    let entries = match std::fs::read_dir(cache_root) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => {
+            return Err(CliShimError::Install {
+                path: cache_root.to_path_buf(),
+                source,
+            })
+        }
+    };
+
+    let mut cached_binaries: Vec<CachedBinary> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let directory = entry.path();
+            let binary_path = directory.join(BINARY_NAME);
+            if !binary_path.is_file() {
+                return None;
+            }
+
+            Some(CachedBinary {
+                version: entry.file_name().to_str()?.to_string(),
+                manifest: read_manifest(&binary_path),
+                path: binary_path,
+            })
+        })
+        .collect();
+    cached_binaries.sort_by(|a, b| a.version.cmp(&b.version));
+
+    Ok(cached_binaries)
+}
+
+/// Removes every cached binary whose version is not contained in `keep`, returning the removed versions.
+///
+/// Any files left in the cache by older versions of this crate which did not track versions are also removed.
+pub(super) fn prune_cached_versions(
+    config: &CliShimConfig,
+    keep: &[&str],
+) -> Result<Vec<String>, CliShimError> {
+    prune_in(&cache_root(config)?, keep)
+}
+
+fn prune_in(cache_root: &Path, keep: &[&str]) -> Result<Vec<String>, CliShimError> {
+    let remove_error = |path: PathBuf| move |source| CliShimError::Install { path, source };
+
+    // Older versions of this crate installed an unversioned binary directly into the cache root.
+    let legacy_binary = cache_root.join("backend_binary");
+    if legacy_binary.is_file() {
+        std::fs::remove_file(&legacy_binary).map_err(remove_error(legacy_binary.clone()))?;
+    }
+
+    let mut removed = Vec::new();
+    for cached_binary in list_in(cache_root)? {
+        if keep.contains(&cached_binary.version.as_str()) {
+            continue;
+        }
+
+        let directory = cache_root.join(&cached_binary.version);
+        std::fs::remove_dir_all(&directory).map_err(remove_error(directory.clone()))?;
+        removed.push(cached_binary.version);
+    }
+
+    Ok(removed)
+}
+
+#[test]
+fn cache_lists_and_prunes_versions() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let cache_root = temp_directory.path();
+
+    for version in ["0.4.5", "0.4.6"] {
+        let binary_path = cache_root.join(version).join(BINARY_NAME);
+        std::fs::create_dir_all(binary_path.parent().unwrap()).unwrap();
+        std::fs::write(&binary_path, install::MOCK_BB_SCRIPT).unwrap();
+        write_manifest(
+            &binary_path,
+            &BinaryManifest {
+                version: version.to_string(),
+                source: "file:///bb".to_string(),
+                sha256: super::digest::sha256_hex(install::MOCK_BB_SCRIPT.as_bytes()),
+            },
+        )
+        .unwrap();
+    }
+    std::fs::write(cache_root.join("backend_binary"), "legacy").unwrap();
+
+    let versions: Vec<_> = list_in(cache_root)
+        .unwrap()
+        .into_iter()
+        .map(|cached_binary| {
+            assert_eq!(
+                cached_binary.manifest.unwrap().version,
+                cached_binary.version
+            );
+            cached_binary.version
+        })
+        .collect();
+    assert_eq!(versions, ["0.4.5", "0.4.6"]);
+
+    let removed = prune_in(cache_root, &["0.4.6"]).unwrap();
+    assert_eq!(removed, ["0.4.5"]);
+    assert!(!cache_root.join("backend_binary").exists());
+    assert_eq!(list_in(cache_root).unwrap().len(), 1);
+
+    drop(temp_directory);
+}
+
+#[test]
+fn versions_cannot_escape_the_cache() {
+    for version in ["", "..", "../0.4.6", "0.4.6/bb"] {
+        assert!(matches!(
+            version_directory(&CliShimConfig::default(), version),
+            Err(CliShimError::InvalidVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{CliShimConfig, CliShimError};
+
+/// How often a running command checks whether it has been cancelled or its timeout has elapsed.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A handle through which the barretenberg commands run by a backend can be cancelled from another thread.
+///
+/// Clones of a handle share its state, so cancelling any one of them cancels every command run by
+/// the backends which they were given to. Once cancelled, a handle stays cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationHandle {
+    pub fn new() -> CancellationHandle {
+        CancellationHandle::default()
+    }
+
+    /// Kills any barretenberg commands which are running and fails any which are subsequently run.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// The conditions under which a running command must be stopped: its timeout elapsing or it being cancelled.
+#[derive(Debug)]
+pub(super) struct Deadline<'a> {
+    command: &'static str,
+    timeout: Option<(Duration, Instant)>,
+    cancellation: Option<&'a CancellationHandle>,
+}
+
+impl<'a> Deadline<'a> {
+    /// Starts the timeout of the `command` run with `config`, failing if the command has already been cancelled.
+    pub(super) fn start(
+        config: &'a CliShimConfig,
+        command: &'static str,
+    ) -> Result<Deadline<'a>, CliShimError> {
+        let deadline = Deadline {
+            command,
+            timeout: config
+                .timeout_for(command)
+                .map(|timeout| (timeout, Instant::now() + timeout)),
+            cancellation: config.cancellation.as_ref(),
+        };
+        deadline.check()?;
+        Ok(deadline)
+    }
+
+    /// Returns whether the command may run forever, in which case there is no need to poll it.
+    pub(super) fn is_unbounded(&self) -> bool {
+        self.timeout.is_none() && self.cancellation.is_none()
+    }
+
+    /// Returns how long to wait before checking the command again, or the error with which it must be stopped.
+    pub(super) fn check(&self) -> Result<Duration, CliShimError> {
+        if self
+            .cancellation
+            .map_or(false, CancellationHandle::is_cancelled)
+        {
+            return Err(CliShimError::Cancelled {
+                command: self.command,
+            });
+        }
+
+        match self.timeout {
+            Some((timeout, expires)) => {
+                let now = Instant::now();
+                if now >= expires {
+                    return Err(CliShimError::Timeout {
+                        command: self.command,
+                        timeout,
+                    });
+                }
+                Ok((expires - now).min(POLL_INTERVAL))
+            }
+            None => Ok(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Kills the process group led by the process `id`, which includes any processes that it has started.
+///
+/// Every barretenberg process is started as the leader of its own process group, so that killing the group
+/// cannot affect this process. The process must not have been waited on, otherwise its ID may have been reused.
+pub(super) fn kill_process_group(id: u32) {
+    if let Ok(id) = libc::pid_t::try_from(id) {
+        // SAFETY: `kill` has no memory safety requirements. Its result is ignored as the group may have already exited.
+        unsafe {
+            libc::kill(-id, libc::SIGKILL);
+        }
+    }
+}
+
+/// Kills `child` along with any processes which it has started, then waits for it to exit.
+pub(super) fn kill_child(child: &mut Child) {
+    kill_process_group(child.id());
+    // The child may have already exited, in which case there is nothing to kill.
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn cancelled_commands_fail_immediately() {
+    let cancellation = CancellationHandle::new();
+    let config = CliShimConfig {
+        cancellation: Some(cancellation.clone()),
+        ..CliShimConfig::default()
+    };
+
+    let deadline = Deadline::start(&config, "prove").unwrap();
+    assert!(!deadline.is_unbounded());
+    assert!(deadline.check().unwrap() <= POLL_INTERVAL);
+
+    cancellation.clone().cancel();
+    assert!(matches!(
+        deadline.check(),
+        Err(CliShimError::Cancelled { command: "prove" })
+    ));
+    assert!(matches!(
+        Deadline::start(&config, "verify"),
+        Err(CliShimError::Cancelled { command: "verify" })
+    ));
+}
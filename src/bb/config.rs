@@ -0,0 +1,177 @@
+#[cfg(feature = "experimental-workers")]
+use std::sync::Arc;
+use std::{
+    collections::BTreeMap, os::unix::process::CommandExt, path::PathBuf, process::Command,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+#[cfg(feature = "experimental-workers")]
+use super::WorkerPool;
+use super::{
+    assert_binary_exists, CancellationHandle, CliShimError, ProgressCallback, ResourceLimits,
+};
+use crate::report::Recorder;
+
+/// Environment variable which sets the path of the barretenberg binary.
+const BINARY_PATH: &str = "BB_BINARY_PATH";
+
+/// Environment variable which sets the URL from which the barretenberg binary is downloaded.
+const BINARY_URL: &str = "BB_BINARY_URL";
+
+/// Environment variable which overrides the expected digest of the downloaded archive.
+///
+/// This must be set when `BB_BINARY_URL` points at a custom build.
+const ARCHIVE_DIGEST_OVERRIDE: &str = "BB_BINARY_SHA256";
+
+/// Environment variable which supplies the expected digest of the extracted `bb` executable.
+const EXECUTABLE_DIGEST_OVERRIDE: &str = "BB_EXECUTABLE_SHA256";
+
+/// Environment variable which, when set, forbids downloading the barretenberg binary.
+const OFFLINE_MODE: &str = "BB_OFFLINE";
+
+/// Environment variable which limits the number of threads used by the barretenberg binary.
+const THREAD_COUNT: &str = "OMP_NUM_THREADS";
+
+/// An operation performed by running the barretenberg binary.
+///
+/// Each operation may be given a timeout of its own and identifies the operation which reported a [`super::ProgressEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// Counting the gates in a circuit.
+    GateCount,
+    /// Creating a proof.
+    Prove,
+    /// Verifying a proof.
+    Verify,
+    /// Generating a Solidity verifier.
+    Contract,
+    /// Generating a verification key.
+    WriteVerificationKey,
+    /// Converting a verification key into field elements.
+    VerificationKeyAsFields,
+}
+
+impl Operation {
+    const ALL: [Operation; 6] = [
+        Operation::GateCount,
+        Operation::Prove,
+        Operation::Verify,
+        Operation::Contract,
+        Operation::WriteVerificationKey,
+        Operation::VerificationKeyAsFields,
+    ];
+
+    /// Returns the operation performed by the barretenberg subcommand `command`, if it is one which callers can observe.
+    pub(super) fn from_command(command: &str) -> Option<Operation> {
+        Operation::ALL
+            .into_iter()
+            .find(|operation| operation.command() == command)
+    }
+
+    /// Returns the barretenberg subcommand which performs this operation.
+    fn command(self) -> &'static str {
+        match self {
+            Operation::GateCount => "gates",
+            Operation::Prove => "prove",
+            Operation::Verify => "verify",
+            Operation::Contract => "contract",
+            Operation::WriteVerificationKey => "write_vk",
+            Operation::VerificationKeyAsFields => "vk_as_fields",
+        }
+    }
+}
+
+/// Configuration shared by every command which is run against the barretenberg binary.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CliShimConfig {
+    /// The path to the barretenberg binary.
+    ///
+    /// If this is not set then the cached binary for the version targeted by this crate is used.
+    pub(crate) binary_path: Option<PathBuf>,
+    /// The URL from which the barretenberg binary for the version targeted by this crate is downloaded.
+    pub(crate) download_url: Option<String>,
+    /// The expected SHA-256 digest of the release archive for the version targeted by this crate.
+    pub(crate) archive_sha256: Option<String>,
+    /// The expected SHA-256 digest of the `bb` executable for the version targeted by this crate.
+    pub(crate) executable_sha256: Option<String>,
+    /// The directory under which the binaries for each barretenberg release are cached.
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// Whether downloading the barretenberg binary is forbidden.
+    pub(crate) offline: bool,
+    /// Whether barretenberg should log its progress, if it has been set.
+    /// Otherwise barretenberg is only verbose while creating proofs.
+    pub(crate) verbose: Option<bool>,
+    /// The maximum number of threads which barretenberg may use.
+    pub(crate) threads: Option<usize>,
+    /// The callback which is notified as barretenberg reaches each phase of an operation.
+    pub(crate) progress: Option<ProgressCallback>,
+    /// The limits on the memory and CPU time which each barretenberg process may use.
+    pub(crate) limits: ResourceLimits,
+    /// The maximum amount of time for which a single command may run, unless its operation has a timeout of its own.
+    pub(crate) timeout: Option<Duration>,
+    /// The maximum amount of time for which the commands performing each operation may run.
+    pub(crate) operation_timeouts: BTreeMap<Operation, Duration>,
+    /// The handle through which running commands may be cancelled.
+    pub(crate) cancellation: Option<CancellationHandle>,
+    /// The performance report to which each command's running time and resource usage is added, if one is being recorded.
+    pub(crate) recorder: Option<Recorder>,
+    /// The long-lived barretenberg processes which commands are sent to, if worker mode is enabled.
+    ///
+    /// The pool is shared between clones of the configuration.
+    #[cfg(feature = "experimental-workers")]
+    pub(crate) workers: Option<Arc<WorkerPool>>,
+    /// Whether inputs and outputs are streamed to barretenberg, rather than written to temporary files,
+    /// if the binary supports it.
+    pub(crate) stream_io: bool,
+}
+
+impl CliShimConfig {
+    /// Reads the configuration from the environment variables previously used to configure this crate.
+    pub(crate) fn from_env() -> CliShimConfig {
+        let var = |name: &str| std::env::var(name).ok();
+
+        CliShimConfig {
+            binary_path: var(BINARY_PATH).map(PathBuf::from),
+            download_url: var(BINARY_URL),
+            archive_sha256: var(ARCHIVE_DIGEST_OVERRIDE),
+            executable_sha256: var(EXECUTABLE_DIGEST_OVERRIDE),
+            cache_dir: None,
+            offline: var(OFFLINE_MODE)
+                .map_or(false, |value| !matches!(value.trim(), "" | "0" | "false")),
+            verbose: None,
+            threads: None,
+            progress: None,
+            limits: ResourceLimits::default(),
+            timeout: None,
+            operation_timeouts: BTreeMap::new(),
+            cancellation: None,
+            recorder: None,
+            #[cfg(feature = "experimental-workers")]
+            workers: None,
+            stream_io: false,
+        }
+    }
+
+    /// Returns the maximum amount of time for which the barretenberg subcommand `command` may run.
+    pub(super) fn timeout_for(&self, command: &str) -> Option<Duration> {
+        Operation::from_command(command)
+            .and_then(|operation| self.operation_timeouts.get(&operation).copied())
+            .or(self.timeout)
+    }
+
+    /// Creates a command which runs the barretenberg binary, downloading the binary first if necessary.
+    pub(super) fn command(&self) -> Result<Command, CliShimError> {
+        let binary_path = assert_binary_exists(self)?;
+        let mut command = Command::new(binary_path);
+        // Each command leads a process group of its own, so that anything it starts is killed along with it.
+        command.process_group(0);
+        self.limits.apply(&mut command);
+        if let Some(threads) = self.threads {
+            command.env(THREAD_COUNT, threads.to_string());
+        }
+        Ok(command)
+    }
+}
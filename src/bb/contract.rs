@@ -1,4 +1,6 @@
-use super::{assert_binary_exists, get_binary_path, CliShimError};
+use std::process::Command;
+
+use super::{run_command, CliShimConfig, CliShimError, OutputFile};
 
 /// VerifyCommand will call the barretenberg binary
 /// to return a solidity library with the verification key
@@ -11,33 +13,42 @@ pub(crate) struct ContractCommand {
     pub(crate) verbose: bool,
     pub(crate) path_to_crs: String,
     pub(crate) path_to_vk: String,
-    pub(crate) path_to_contract: String,
+    pub(crate) contract: OutputFile,
 }
 
 impl ContractCommand {
-    pub(crate) fn run(self) -> Result<(), CliShimError> {
-        assert_binary_exists();
-        let mut command = std::process::Command::new(get_binary_path());
+    pub(crate) fn run(self, config: &CliShimConfig) -> Result<Vec<u8>, CliShimError> {
+        let command = self.command(config)?;
+        let output = run_command(config, "contract", command, None)?;
+        self.contract.read("contract", output)
+    }
+
+    /// Runs the command without blocking, killing barretenberg if the returned future is dropped.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async(self, config: &CliShimConfig) -> Result<Vec<u8>, CliShimError> {
+        let command = self.command(config)?;
+        let output =
+            super::async_process::run_command_async(config, "contract", command, None).await?;
+        self.contract.read("contract", output)
+    }
+
+    fn command(&self, config: &CliShimConfig) -> Result<Command, CliShimError> {
+        let mut command = config.command()?;
 
         command
             .arg("contract")
             .arg("-c")
-            .arg(self.path_to_crs)
+            .arg(&self.path_to_crs)
             .arg("-k")
-            .arg(self.path_to_vk)
+            .arg(&self.path_to_vk)
             .arg("-o")
-            .arg(self.path_to_contract);
+            .arg(self.contract.path());
 
         if self.verbose {
             command.arg("-v");
         }
 
-        let output = command.output().expect("Failed to execute command");
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(CliShimError(String::from_utf8(output.stderr).unwrap()))
-        }
+        Ok(command)
     }
 }
 
@@ -47,6 +58,7 @@ fn contract_command() {
 
     let path_to_1_mul = "./src/1_mul.bytecode";
 
+    let config = CliShimConfig::from_env();
     let temp_directory = tempdir().expect("could not create a temporary directory");
     let temp_directory_path = temp_directory.path();
     let path_to_crs = temp_directory_path.join("crs");
@@ -61,15 +73,15 @@ fn contract_command() {
         path_to_crs: path_to_crs.to_str().unwrap().to_string(),
     };
 
-    assert!(write_vk_command.run().is_ok());
+    assert!(write_vk_command.run(&config).is_ok());
 
     let contract_command = ContractCommand {
         verbose: true,
         path_to_vk: path_to_vk.to_str().unwrap().to_string(),
         path_to_crs: path_to_crs.to_str().unwrap().to_string(),
-        path_to_contract: path_to_contract.to_str().unwrap().to_string(),
+        contract: OutputFile::Path(path_to_contract.to_str().unwrap().to_string()),
     };
 
-    assert!(contract_command.run().is_ok());
+    assert!(contract_command.run(&config).is_ok());
     drop(temp_directory);
 }
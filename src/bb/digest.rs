@@ -0,0 +1,208 @@
+use sha2::{Digest, Sha256};
+
+use super::{CliShimConfig, CliShimError, VERSION};
+
+/// SHA-256 digests of the release archives published for [`VERSION`](super::VERSION), indexed by archive name.
+///
+/// These must be updated alongside `VERSION` and match the hashes pinned in `flake.nix`.
+/// The `aarch64-apple-darwin` archive is not pinned there, so can only be downloaded once its digest
+/// is configured through `BB_BINARY_SHA256`.
+const PINNED_ARCHIVE_DIGESTS: &[(&str, &str)] = &[
+    (
+        "barretenberg-x86_64-linux-gnu.tar.gz",
+        "00a02f9f94908a63555f0f26463029dafca16d437cb97b08282924b7b3d925dc",
+    ),
+    (
+        "barretenberg-x86_64-apple-darwin.tar.gz",
+        "a0365246cbe12542c9368060b3ed29bc09d6d470303185a23d5ffd0dabcea90d",
+    ),
+];
+
+/// The digests which a barretenberg release must match before being installed.
+#[derive(Debug)]
+pub(super) struct ExpectedDigests {
+    archive: Option<String>,
+    executable: Option<String>,
+}
+
+impl ExpectedDigests {
+    /// Determines the expected digests for the release archive of `version` named `archive_name`.
+    ///
+    /// An `archive_sha256` supplied by the caller takes precedence over any other digest. Digests supplied through
+    /// `config` describe the release targeted by this crate, so take precedence over the pinned digests for that
+    /// release only. No archive name should be provided if the download URL has been overridden,
+    /// as the pinned digests then describe a different archive.
+    pub(super) fn new(
+        config: &CliShimConfig,
+        version: &str,
+        archive_name: Option<&str>,
+        archive_sha256: Option<&str>,
+    ) -> ExpectedDigests {
+        let configured = |digest: &Option<String>| {
+            digest
+                .as_deref()
+                .filter(|_| version == VERSION)
+                .map(normalize)
+        };
+        let archive = archive_sha256
+            .map(normalize)
+            .or_else(|| configured(&config.archive_sha256))
+            .or_else(|| {
+                let archive_name = archive_name?;
+                PINNED_ARCHIVE_DIGESTS
+                    .iter()
+                    .find(|(name, _)| *name == archive_name)
+                    .map(|(_, digest)| digest.to_string())
+            });
+        let executable = configured(&config.executable_sha256);
+
+        ExpectedDigests {
+            archive,
+            executable,
+        }
+    }
+
+    /// Determines the expected digests for an archive downloaded from `url`.
+    ///
+    /// Archives fetched from the network must be verified, so this fails if no digest is known.
+    pub(super) fn for_download(
+        config: &CliShimConfig,
+        version: &str,
+        url: &str,
+        archive_name: Option<&str>,
+        archive_sha256: Option<&str>,
+    ) -> Result<ExpectedDigests, CliShimError> {
+        let expected_digests = ExpectedDigests::new(config, version, archive_name, archive_sha256);
+        if expected_digests.archive.is_none() {
+            return Err(CliShimError::MissingDigest {
+                url: url.to_string(),
+            });
+        }
+        Ok(expected_digests)
+    }
+
+    /// Checks the digest of a release archive, if one is expected.
+    pub(super) fn verify_archive(&self, archive: &[u8]) -> Result<(), CliShimError> {
+        match &self.archive {
+            Some(expected) => verify_digest("archive", archive, expected),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the digest of the `bb` executable, if one is expected.
+    pub(super) fn verify_executable(&self, executable: &[u8]) -> Result<(), CliShimError> {
+        match &self.executable {
+            Some(expected) => verify_digest("executable", executable, expected),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the lowercase hex encoding of the SHA-256 digest of `bytes`.
+pub(super) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Checks that `bytes` hash to the `expected` SHA-256 digest.
+///
+/// `artifact` describes what is being verified and is only used for error reporting.
+pub(super) fn verify_digest(
+    artifact: &'static str,
+    bytes: &[u8],
+    expected: &str,
+) -> Result<(), CliShimError> {
+    let found = sha256_hex(bytes);
+    if found == normalize(expected) {
+        Ok(())
+    } else {
+        Err(CliShimError::DigestMismatch {
+            artifact,
+            expected: expected.to_string(),
+            found,
+        })
+    }
+}
+
+fn normalize(digest: &str) -> String {
+    digest.trim().to_ascii_lowercase()
+}
+
+#[test]
+fn digest_verification() {
+    const EMPTY_DIGEST: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    assert_eq!(sha256_hex(&[]), EMPTY_DIGEST);
+    assert!(verify_digest("archive", &[], EMPTY_DIGEST).is_ok());
+    assert!(verify_digest("archive", &[], &EMPTY_DIGEST.to_ascii_uppercase()).is_ok());
+
+    let mismatch = verify_digest("archive", b"tampered", EMPTY_DIGEST);
+    assert!(matches!(
+        mismatch,
+        Err(CliShimError::DigestMismatch {
+            artifact: "archive",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn downloaded_archives_must_be_verified() {
+    let config = CliShimConfig::default();
+    let url = "https://example.com/barretenberg.tar.gz";
+    for archive_name in [
+        "barretenberg-x86_64-linux-gnu.tar.gz",
+        "barretenberg-x86_64-apple-darwin.tar.gz",
+    ] {
+        assert!(
+            ExpectedDigests::for_download(&config, VERSION, url, Some(archive_name), None).is_ok()
+        );
+    }
+
+    // Archives whose digests are not pinned, or which this crate does not know of, need a configured digest.
+    let unpinned_archive = Some("barretenberg-aarch64-apple-darwin.tar.gz");
+    for archive_name in [unpinned_archive, None] {
+        assert!(matches!(
+            ExpectedDigests::for_download(&config, VERSION, url, archive_name, None),
+            Err(CliShimError::MissingDigest { .. })
+        ));
+    }
+
+    let config = CliShimConfig {
+        archive_sha256: Some(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string(),
+        ),
+        ..CliShimConfig::default()
+    };
+    assert!(ExpectedDigests::for_download(&config, VERSION, url, unpinned_archive, None).is_ok());
+}
+
+#[test]
+fn configured_digests_only_apply_to_the_targeted_release() {
+    const DIGEST: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const OTHER_DIGEST: &str = "a0365246cbe12542c9368060b3ed29bc09d6d470303185a23d5ffd0dabcea90d";
+
+    let config = CliShimConfig {
+        archive_sha256: Some(DIGEST.to_string()),
+        executable_sha256: Some(DIGEST.to_string()),
+        ..CliShimConfig::default()
+    };
+    let url = "https://example.com/barretenberg.tar.gz";
+
+    let expected = ExpectedDigests::new(&config, VERSION, None, None);
+    assert_eq!(expected.archive.as_deref(), Some(DIGEST));
+    assert_eq!(expected.executable.as_deref(), Some(DIGEST));
+
+    let expected = ExpectedDigests::new(&config, "0.3.6", None, None);
+    assert_eq!(expected.archive, None);
+    assert_eq!(expected.executable, None);
+    assert!(matches!(
+        ExpectedDigests::for_download(&config, "0.3.6", url, None, None),
+        Err(CliShimError::MissingDigest { .. })
+    ));
+
+    let expected = ExpectedDigests::new(&config, "0.3.6", None, Some(OTHER_DIGEST));
+    assert_eq!(expected.archive.as_deref(), Some(OTHER_DIGEST));
+}
@@ -1,71 +1,109 @@
-use super::{assert_binary_exists, get_binary_path};
+use std::process::Command;
+
+use super::{run_command, CliShimConfig, CliShimError, InputFile};
 
 /// GatesCommand will call the barretenberg binary
 /// to return the number of gates needed to create a proof
 /// for the given bytecode.
 pub(crate) struct GatesCommand {
     pub(crate) path_to_crs: String,
-    pub(crate) path_to_bytecode: String,
+    pub(crate) bytecode: InputFile,
 }
 
 impl GatesCommand {
-    pub(crate) fn run(self) -> u32 {
-        assert_binary_exists();
-        let output = std::process::Command::new(get_binary_path())
+    pub(crate) fn run(self, config: &CliShimConfig) -> Result<u32, CliShimError> {
+        let command = self.command(config)?;
+        let output = run_command(config, "gates", command, self.bytecode.stdin())?;
+        parse_gate_count(&output.stdout)
+    }
+
+    /// Runs the command without blocking, killing barretenberg if the returned future is dropped.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async(self, config: &CliShimConfig) -> Result<u32, CliShimError> {
+        let command = self.command(config)?;
+        let output = super::async_process::run_command_async(
+            config,
+            "gates",
+            command,
+            self.bytecode.stdin(),
+        )
+        .await?;
+        parse_gate_count(&output.stdout)
+    }
+
+    fn command(&self, config: &CliShimConfig) -> Result<Command, CliShimError> {
+        let mut command = config.command()?;
+
+        command
             .arg("gates")
             .arg("-c")
-            .arg(self.path_to_crs)
+            .arg(&self.path_to_crs)
             .arg("-b")
-            .arg(self.path_to_bytecode)
-            .output()
-            .expect("Failed to execute command");
-
-        if !output.status.success() {
-            panic!(
-                "gates command encountered an error: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-        // Note: barretenberg includes the newline, so that subsequent prints to stdout
-        // are not on the same line as the gates output.
-
-        // Ensure we got the expected number of bytes
-        if output.stdout.len() != 8 {
-            panic!("Unexpected 8 bytes, received {}", output.stdout.len());
-        }
-
-        // Convert bytes to u64 in little-endian format
-        let value = u64::from_le_bytes([
-            output.stdout[0],
-            output.stdout[1],
-            output.stdout[2],
-            output.stdout[3],
-            output.stdout[4],
-            output.stdout[5],
-            output.stdout[6],
-            output.stdout[7],
-        ]);
-
-        value as u32
+            .arg(self.bytecode.path());
+
+        Ok(command)
     }
 }
 
+/// Parses the number of gates from the stdout of the `gates` command.
+fn parse_gate_count(stdout: &[u8]) -> Result<u32, CliShimError> {
+    // Note: barretenberg includes the newline, so that subsequent prints to stdout
+    // are not on the same line as the gates output.
+
+    // Ensure we got the expected number of bytes
+    let bytes: [u8; 8] = stdout
+        .try_into()
+        .map_err(|_| CliShimError::MalformedOutput {
+            command: "gates",
+            reason: format!("expected 8 bytes, received {}", stdout.len()),
+        })?;
+
+    // Convert bytes to u64 in little-endian format
+    let value = u64::from_le_bytes(bytes);
+
+    u32::try_from(value).map_err(|_| CliShimError::MalformedOutput {
+        command: "gates",
+        reason: format!("gate count {value} does not fit into a u32"),
+    })
+}
+
 #[test]
 fn gate_command() {
     use tempfile::tempdir;
 
     let path_to_1_mul = "./src/1_mul.bytecode";
 
+    let config = CliShimConfig::from_env();
     let temp_directory = tempdir().expect("could not create a temporary directory");
     let temp_directory_path = temp_directory.path();
     let path_to_crs = temp_directory_path.join("crs");
 
     let gate_command = GatesCommand {
         path_to_crs: path_to_crs.to_str().unwrap().to_string(),
-        path_to_bytecode: path_to_1_mul.to_string(),
+        bytecode: InputFile::Path(path_to_1_mul.to_string()),
     };
 
-    let output = gate_command.run();
+    let output = gate_command.run(&config).unwrap();
     assert_eq!(output, 2775);
     drop(temp_directory);
 }
+
+#[test]
+fn malformed_gates_output_is_an_error() {
+    assert_eq!(parse_gate_count(&2775u64.to_le_bytes()).unwrap(), 2775);
+
+    assert!(matches!(
+        parse_gate_count(&[1, 2, 3]),
+        Err(CliShimError::MalformedOutput {
+            command: "gates",
+            ..
+        })
+    ));
+    assert!(matches!(
+        parse_gate_count(&u64::MAX.to_le_bytes()),
+        Err(CliShimError::MalformedOutput {
+            command: "gates",
+            ..
+        })
+    ));
+}
@@ -0,0 +1,368 @@
+use std::{
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    digest::{sha256_hex, verify_digest, ExpectedDigests},
+    get_bb_download_url, version, BinaryManifest, CliShimConfig, CliShimError,
+};
+
+/// Name of the barretenberg executable within the release archives.
+const ARCHIVE_BINARY_NAME: &str = "bb";
+
+/// Downloads the barretenberg release `version` for the target platform and installs it to `binary_path`.
+///
+/// The release archive must match `archive_sha256` if it is provided.
+pub(super) fn download_bb_binary(
+    config: &CliShimConfig,
+    version: &str,
+    archive_sha256: Option<&str>,
+    binary_path: &Path,
+) -> Result<BinaryManifest, CliShimError> {
+    let (url, archive_name) = get_bb_download_url(config, version)?;
+    if config.offline {
+        return Err(CliShimError::Offline {
+            path: binary_path.to_path_buf(),
+            url,
+        });
+    }
+
+    // Determine which digests the downloaded files must match before doing any work
+    let expected_digests =
+        ExpectedDigests::for_download(config, version, &url, archive_name, archive_sha256)?;
+
+    // Download sources
+    let compressed_file = download_binary_from_url(&url)?;
+    expected_digests.verify_archive(&compressed_file)?;
+
+    let binary = unpack_binary(&compressed_file)?;
+    expected_digests.verify_executable(&binary)?;
+
+    let sha256 = install_executable(&binary, binary_path)?;
+    Ok(BinaryManifest {
+        version: version.to_string(),
+        source: url,
+        sha256,
+    })
+}
+
+/// Installs the barretenberg binary at `source` to `binary_path` without accessing the network.
+///
+/// `source` may be a release archive (`.tar.gz`), a directory containing an extracted release archive
+/// or a prebuilt `bb` executable. The binary is checked to be a working barretenberg binary for the release `version`
+/// before it is installed.
+pub(super) fn install_from_path(
+    config: &CliShimConfig,
+    version: &str,
+    source: &Path,
+    binary_path: &Path,
+) -> Result<BinaryManifest, CliShimError> {
+    let read_error = |source_error| CliShimError::Install {
+        path: source.to_path_buf(),
+        source: source_error,
+    };
+
+    let binary = if source.is_dir() {
+        std::fs::read(source.join(ARCHIVE_BINARY_NAME)).map_err(read_error)?
+    } else {
+        let contents = std::fs::read(source).map_err(read_error)?;
+        if is_gzip(&contents) {
+            let archive_name = source.file_name().and_then(|name| name.to_str());
+            ExpectedDigests::new(config, version, archive_name, None).verify_archive(&contents)?;
+            unpack_binary(&contents)?
+        } else {
+            contents
+        }
+    };
+    ExpectedDigests::new(config, version, None, None).verify_executable(&binary)?;
+
+    // Check that the binary works before replacing any existing installation.
+    let temp_directory = tempfile::tempdir().map_err(CliShimError::Unpack)?;
+    let candidate_path = temp_directory.path().join(ARCHIVE_BINARY_NAME);
+    install_executable(&binary, &candidate_path)?;
+    // A binary which cannot report its version is taken to belong to the release which it is being installed as,
+    // which is then recorded in its manifest.
+    if let Some(detected_version) = version::detect_version(&candidate_path)? {
+        version::check_version(&candidate_path, version, &detected_version)?;
+    }
+
+    let sha256 = install_executable(&binary, binary_path)?;
+    Ok(BinaryManifest {
+        version: version.to_string(),
+        source: source.display().to_string(),
+        sha256,
+    })
+}
+
+/// Checks that the binary at `binary_path` behaves like a barretenberg binary.
+pub(super) fn probe_binary(binary_path: &Path) -> Result<(), CliShimError> {
+    let invalid_binary = |reason: String| CliShimError::InvalidBinary {
+        path: binary_path.to_path_buf(),
+        reason,
+    };
+
+    // When run without a command, barretenberg prints a fixed message to stderr.
+    let output = std::process::Command::new(binary_path)
+        .output()
+        .map_err(|error| invalid_binary(format!("could not be executed: {error}")))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.trim_end() == "No command provided." {
+        Ok(())
+    } else {
+        Err(invalid_binary(format!("unexpected response: {stderr}")))
+    }
+}
+
+/// Extracts the `bb` executable from a gzipped release archive.
+fn unpack_binary(compressed_file: &[u8]) -> Result<Vec<u8>, CliShimError> {
+    use flate2::read::GzDecoder;
+    use tar::Archive;
+
+    let gz_decoder = GzDecoder::new(Cursor::new(compressed_file));
+    let mut archive = Archive::new(gz_decoder);
+
+    for entry in archive.entries().map_err(CliShimError::Unpack)? {
+        let mut entry = entry.map_err(CliShimError::Unpack)?;
+        let is_binary = entry
+            .path()
+            .map_err(CliShimError::Unpack)?
+            .file_name()
+            .map_or(false, |name| name == ARCHIVE_BINARY_NAME);
+
+        if is_binary {
+            let mut binary = Vec::new();
+            entry
+                .read_to_end(&mut binary)
+                .map_err(CliShimError::Unpack)?;
+            return Ok(binary);
+        }
+    }
+
+    Err(CliShimError::Unpack(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("archive does not contain a `{ARCHIVE_BINARY_NAME}` executable"),
+    )))
+}
+
+/// Runs `install` while holding an exclusive lock on the installation of `binary_path`.
+///
+/// This prevents processes which are racing to install the same binary from interfering with each other.
+/// The lock is held on a separate file alongside the binary, as the binary itself is replaced when installed.
+pub(super) fn with_install_lock<T>(
+    binary_path: &Path,
+    install: impl FnOnce() -> Result<T, CliShimError>,
+) -> Result<T, CliShimError> {
+    use fs2::FileExt;
+
+    let mut lock_path = binary_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+    let lock_error = |source| CliShimError::Install {
+        path: lock_path.clone(),
+        source,
+    };
+
+    if let Some(parent) = lock_path.parent() {
+        std::fs::create_dir_all(parent).map_err(lock_error)?;
+    }
+    let lock_file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(lock_error)?;
+    lock_file.lock_exclusive().map_err(lock_error)?;
+
+    let result = install();
+
+    // The lock is also released when the file is closed, so a failure to unlock is not an error.
+    let _ = FileExt::unlock(&lock_file);
+    result
+}
+
+/// Writes `binary` to `binary_path` and marks it as executable, returning the digest of the installed binary.
+///
+/// The binary is written to a temporary file in the destination directory and then renamed into place,
+/// so that other processes never observe a partially written executable.
+fn install_executable(binary: &[u8], binary_path: &Path) -> Result<String, CliShimError> {
+    let install_error = |source| CliShimError::Install {
+        path: binary_path.to_path_buf(),
+        source,
+    };
+
+    // Create directory to place binary in.
+    let parent = match binary_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(parent).map_err(install_error)?;
+
+    let mut temp_file = tempfile::NamedTempFile::new_in(parent).map_err(install_error)?;
+    temp_file.write_all(binary).map_err(install_error)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        temp_file
+            .as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o755))
+            .map_err(install_error)?;
+    }
+    temp_file.as_file().sync_all().map_err(install_error)?;
+
+    // Ensure that the binary being installed is the one which we verified
+    let written_binary = std::fs::read(temp_file.path()).map_err(install_error)?;
+    let sha256 = sha256_hex(binary);
+    verify_digest("installed executable", &written_binary, &sha256)?;
+
+    temp_file
+        .persist(binary_path)
+        .map_err(|error| install_error(error.error))?;
+
+    // Make sure that the rename itself is durable.
+    #[cfg(unix)]
+    std::fs::File::open(parent)
+        .and_then(|directory| directory.sync_all())
+        .map_err(install_error)?;
+
+    // Any previous binary at this path may have been a different version.
+    version::forget_probe(binary_path);
+
+    Ok(sha256)
+}
+
+/// Try to download the specified URL into a buffer which is returned.
+fn download_binary_from_url(url: &str) -> Result<Vec<u8>, CliShimError> {
+    let download_error = |error: reqwest::Error| CliShimError::Download {
+        url: url.to_string(),
+        reason: error.to_string(),
+    };
+
+    let response = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .map_err(download_error)?;
+
+    let bytes = response.bytes().map_err(download_error)?;
+
+    Ok(bytes.to_vec())
+}
+
+fn is_gzip(contents: &[u8]) -> bool {
+    contents.starts_with(&[0x1f, 0x8b])
+}
+
+#[cfg(test)]
+pub(super) const MOCK_BB_SCRIPT: &str = "#!/bin/sh\necho 'No command provided.' >&2\nexit 1\n";
+
+#[test]
+fn install_from_local_archive() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let temp_directory_path = temp_directory.path();
+
+    // Build an archive with the same layout as the release archives.
+    let archive_path = temp_directory_path.join("barretenberg.tar.gz");
+    let archive_file = std::fs::File::create(&archive_path).unwrap();
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        archive_file,
+        flate2::Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(MOCK_BB_SCRIPT.len() as u64);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "bb", MOCK_BB_SCRIPT.as_bytes())
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let binary_path = temp_directory_path.join("installed").join("backend_binary");
+    let manifest = install_from_path(
+        &CliShimConfig::default(),
+        "0.4.6",
+        &archive_path,
+        &binary_path,
+    )
+    .unwrap();
+    assert_eq!(manifest.sha256, sha256_hex(MOCK_BB_SCRIPT.as_bytes()));
+    assert_eq!(
+        std::fs::read(&binary_path).unwrap(),
+        MOCK_BB_SCRIPT.as_bytes()
+    );
+    assert!(probe_binary(&binary_path).is_ok());
+
+    drop(temp_directory);
+}
+
+#[test]
+fn concurrent_installs_do_not_interfere() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let temp_directory_path = temp_directory.path();
+
+    let source_path = temp_directory_path.join("bb");
+    super::write_mock_binary(&source_path, MOCK_BB_SCRIPT);
+    let binary_path = temp_directory_path.join("installed").join("bb");
+
+    let installers: Vec<_> = (0..8)
+        .map(|_| {
+            let source_path = source_path.clone();
+            let binary_path = binary_path.clone();
+            std::thread::spawn(move || {
+                with_install_lock(&binary_path, || {
+                    if binary_path.exists() {
+                        return Ok(None);
+                    }
+                    install_from_path(
+                        &CliShimConfig::default(),
+                        "0.4.6",
+                        &source_path,
+                        &binary_path,
+                    )
+                    .map(Some)
+                })
+            })
+        })
+        .collect();
+
+    let installations: Vec<_> = installers
+        .into_iter()
+        .map(|installer| installer.join().unwrap().unwrap())
+        .collect();
+
+    // Only the first installer to take the lock should have installed the binary.
+    assert_eq!(installations.iter().flatten().count(), 1);
+    assert_eq!(
+        std::fs::read(&binary_path).unwrap(),
+        MOCK_BB_SCRIPT.as_bytes()
+    );
+
+    drop(temp_directory);
+}
+
+#[test]
+fn install_rejects_invalid_binary() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let temp_directory_path = temp_directory.path();
+
+    let source_path = temp_directory_path.join("bb");
+    std::fs::write(&source_path, "#!/bin/sh\necho 'not barretenberg' >&2\n").unwrap();
+
+    let binary_path = temp_directory_path.join("backend_binary");
+    let result = install_from_path(
+        &CliShimConfig::default(),
+        "0.4.6",
+        &source_path,
+        &binary_path,
+    );
+    assert!(matches!(result, Err(CliShimError::InvalidBinary { .. })));
+    assert!(!binary_path.exists());
+
+    drop(temp_directory);
+}
@@ -0,0 +1,229 @@
+use std::{
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Command, Output},
+    time::Duration,
+};
+
+use super::{CliShimError, ResourceUsage};
+
+/// Messages which barretenberg writes to stderr when it fails to allocate memory.
+const ALLOCATION_FAILURES: [&str; 3] = ["bad_alloc", "Cannot allocate memory", "out of memory"];
+
+/// Limits on the resources which may be used by each barretenberg process.
+///
+/// These are applied as resource limits (rlimits), which the kernel enforces on the process itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ResourceLimits {
+    /// The maximum size in bytes of the process's virtual memory.
+    pub(crate) memory: Option<u64>,
+    /// The maximum amount of CPU time which the process may consume across all of its threads.
+    pub(crate) cpu_time: Option<Duration>,
+}
+
+impl ResourceLimits {
+    /// Applies the limits to the processes spawned by `command`.
+    pub(super) fn apply(self, command: &mut Command) {
+        if self.memory.is_none() && self.cpu_time.is_none() {
+            return;
+        }
+
+        // SAFETY: the closure only calls `setrlimit`, which is async-signal-safe.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(memory) = self.memory {
+                    set_limit(libc::RLIMIT_AS, memory, memory)?;
+                }
+                if let Some(cpu_time) = self.cpu_time {
+                    // The kernel sends `SIGXCPU` at the soft limit, which distinguishes exceeding it from
+                    // being killed for any other reason. The hard limit kills the process if it ignores the signal.
+                    let seconds = cpu_seconds(cpu_time);
+                    set_limit(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Returns the `output` of the `name` command, unless it shows that the command was killed for exceeding a limit.
+    ///
+    /// The resources which the command used are taken into account if its `usage` is known.
+    pub(super) fn check(
+        &self,
+        name: &'static str,
+        output: Output,
+        usage: Option<ResourceUsage>,
+    ) -> Result<Output, CliShimError> {
+        match self.exceeded(name, &output, usage) {
+            Some(error) => Err(error),
+            None => Ok(output),
+        }
+    }
+
+    fn exceeded(
+        &self,
+        name: &'static str,
+        output: &Output,
+        usage: Option<ResourceUsage>,
+    ) -> Option<CliShimError> {
+        if output.status.success() {
+            return None;
+        }
+
+        if let Some(cpu_time) = self.cpu_time {
+            // A process which ignores `SIGXCPU` is sent `SIGKILL` at the hard limit, which is only attributed
+            // to the limit if the process is known to have used that much CPU time.
+            let limit = Duration::from_secs(cpu_seconds(cpu_time));
+            let reached_limit = usage.map_or(false, |usage| {
+                usage.user_cpu_time + usage.system_cpu_time >= limit
+            });
+            let signal = output.status.signal();
+            if signal == Some(libc::SIGXCPU) || (signal == Some(libc::SIGKILL) && reached_limit) {
+                return Some(CliShimError::CpuTimeLimitExceeded {
+                    command: name,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(memory) = self.memory {
+            // The memory limit makes allocations fail rather than having the kernel kill the process,
+            // so barretenberg reports the failure before aborting. Crashes which follow no such report
+            // are not attributed to the limit.
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if ALLOCATION_FAILURES
+                .iter()
+                .any(|message| stderr.contains(message))
+            {
+                return Some(CliShimError::MemoryLimitExceeded {
+                    command: name,
+                    limit: memory,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Rounds `cpu_time` up to the whole number of seconds in which CPU time limits are expressed.
+fn cpu_seconds(cpu_time: Duration) -> u64 {
+    let seconds = cpu_time.as_secs() + u64::from(cpu_time.subsec_nanos() > 0);
+    seconds.max(1)
+}
+
+fn set_limit(resource: LimitResource, soft: u64, hard: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid `rlimit` which outlives the call.
+    if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// The type which identifies a resource to `setrlimit`, which differs between platforms.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type LimitResource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type LimitResource = libc::c_int;
+
+#[test]
+fn limits_are_applied_to_the_binary() {
+    use tempfile::tempdir;
+
+    use super::CliShimConfig;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    super::write_mock_binary(
+        &binary_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\necho \"$(ulimit -v) $(ulimit -t)\"\n",
+    );
+
+    let config = CliShimConfig {
+        binary_path: Some(binary_path.clone()),
+        limits: ResourceLimits {
+            memory: Some(1 << 30),
+            cpu_time: Some(Duration::from_millis(1500)),
+        },
+        ..CliShimConfig::default()
+    };
+    let output = super::run_command(&config, "prove", config.command().unwrap(), None).unwrap();
+    assert_eq!(output.stdout, b"1048576 2\n");
+
+    // Exceeding the CPU time limit should be reported as such, rather than as a generic failure.
+    super::write_mock_binary(
+        &binary_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\nwhile :; do :; done\n",
+    );
+    let config = CliShimConfig {
+        limits: ResourceLimits {
+            memory: None,
+            cpu_time: Some(Duration::from_secs(1)),
+        },
+        ..config
+    };
+    let result = super::run_command(&config, "prove", config.command().unwrap(), None);
+    assert!(matches!(
+        result,
+        Err(CliShimError::CpuTimeLimitExceeded {
+            command: "prove",
+            ..
+        })
+    ));
+
+    // Even if the binary ignores the signal sent at the limit and is killed outright.
+    super::write_mock_binary(
+        &binary_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\ntrap '' XCPU\nwhile :; do :; done\n",
+    );
+    let result = super::run_command(&config, "prove", config.command().unwrap(), None);
+    assert!(matches!(
+        result,
+        Err(CliShimError::CpuTimeLimitExceeded {
+            command: "prove",
+            ..
+        })
+    ));
+
+    // As should failing to allocate memory.
+    super::write_mock_binary(
+        &binary_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\necho \"terminate called after throwing an instance of 'std::bad_alloc'\" >&2\nkill -ABRT $$\n",
+    );
+    let config = CliShimConfig {
+        limits: ResourceLimits {
+            memory: Some(1 << 30),
+            cpu_time: None,
+        },
+        ..config
+    };
+    let result = super::run_predicate_command(&config, "verify", config.command().unwrap(), None);
+    assert!(matches!(
+        result,
+        Err(CliShimError::MemoryLimitExceeded {
+            command: "verify",
+            limit: 1073741824,
+        })
+    ));
+
+    // Crashes which are not caused by failing to allocate memory are reported as ordinary failures.
+    for signal in ["SEGV", "ABRT", "KILL"] {
+        super::write_mock_binary(
+            &binary_path,
+            &format!(
+                "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\nkill -{signal} $$\n"
+            ),
+        );
+        let result = super::run_command(&config, "prove", config.command().unwrap(), None);
+        assert!(
+            matches!(result, Err(CliShimError::CommandFailed { .. })),
+            "SIG{signal} was misreported: {result:?}"
+        );
+    }
+
+    drop(temp_directory);
+}
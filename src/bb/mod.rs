@@ -1,123 +1,596 @@
 // Reference: https://github.com/AztecProtocol/aztec-packages/blob/master/circuits/cpp/barretenberg/cpp/src/barretenberg/bb/main.cpp
 
+#[cfg(feature = "async")]
+mod async_process;
+mod cache;
+mod cancellation;
+mod config;
 mod contract;
+mod digest;
 mod gates;
+mod install;
+mod limits;
+mod progress;
 mod prove;
 mod prove_and_verify;
+mod streaming;
+mod usage;
 mod verify;
+mod version;
+mod vk_as_fields;
+#[cfg(feature = "experimental-workers")]
+mod worker;
 mod write_vk;
 
-use std::{io::Cursor, path::PathBuf};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Output, Stdio},
+    time::{Duration, Instant},
+};
 
-use const_format::formatcp;
+use cancellation::Deadline;
+use progress::OutputLines;
+
+use crate::trace;
+
+pub use cache::{BinaryManifest, CachedBinary};
+pub use cancellation::CancellationHandle;
+pub(crate) use config::CliShimConfig;
+pub use config::Operation;
 pub(crate) use contract::ContractCommand;
 pub(crate) use gates::GatesCommand;
+pub(crate) use limits::ResourceLimits;
+pub(crate) use progress::ProgressCallback;
+pub use progress::{ProgressEvent, ProgressPhase};
 pub(crate) use prove::ProveCommand;
+pub(crate) use streaming::{supports_streaming, InputFile, OutputFile};
+pub(crate) use usage::ResourceUsage;
 pub(crate) use verify::VerifyCommand;
+pub(crate) use vk_as_fields::VkAsFieldsCommand;
+#[cfg(feature = "experimental-workers")]
+pub(crate) use worker::WorkerPool;
 pub(crate) use write_vk::WriteVkCommand;
 
 #[derive(Debug, thiserror::Error)]
-#[error("Error communicating with barretenberg binary {0}")]
-pub(crate) struct CliShimError(String);
+pub(crate) enum CliShimError {
+    #[error("Barretenberg binary could not be found at {}", .0.display())]
+    BinaryNotFound(PathBuf),
+    #[error("Failed to download barretenberg binary from {url}: {reason}")]
+    Download { url: String, reason: String },
+    #[error("Barretenberg binary is missing from {} and offline mode forbids downloading it from {url}", path.display())]
+    Offline { path: PathBuf, url: String },
+    #[error("{} is not a valid barretenberg binary: {reason}", path.display())]
+    InvalidBinary { path: PathBuf, reason: String },
+    #[error("Barretenberg binary at {} is version {found} but this backend requires version {expected}", path.display())]
+    IncompatibleBinary {
+        path: PathBuf,
+        expected: String,
+        found: String,
+    },
+    #[error("Barretenberg binary at {} does not report its version and has no manifest recording it, so cannot be checked against version {expected}", path.display())]
+    UnknownVersion { path: PathBuf, expected: String },
+    #[error("Barretenberg binaries are not published for {arch} {os}")]
+    UnsupportedPlatform {
+        os: &'static str,
+        arch: &'static str,
+    },
+    #[error("{0} is not a valid barretenberg version")]
+    InvalidVersion(String),
+    #[error("No SHA-256 digest is pinned for {url}, provide the expected digest of the archive through `BB_BINARY_SHA256` or alongside its URL")]
+    MissingDigest { url: String },
+    #[error("SHA-256 digest of barretenberg {artifact} does not match: expected {expected}, found {found}")]
+    DigestMismatch {
+        artifact: &'static str,
+        expected: String,
+        found: String,
+    },
+    #[error("Failed to unpack barretenberg archive: {0}")]
+    Unpack(std::io::Error),
+    #[error("Failed to install barretenberg binary to {}: {source}", path.display())]
+    Install {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to spawn barretenberg binary for `{command}` command: {source}")]
+    Spawn {
+        command: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Barretenberg `{command}` command did not complete within {timeout:?}")]
+    Timeout {
+        command: &'static str,
+        timeout: Duration,
+    },
+    #[error("Barretenberg `{command}` command was cancelled")]
+    Cancelled { command: &'static str },
+    #[error("Barretenberg `{command}` command was killed for exceeding its memory limit of {limit} bytes")]
+    MemoryLimitExceeded { command: &'static str, limit: u64 },
+    #[error(
+        "Barretenberg `{command}` command was killed for exceeding its CPU time limit of {limit:?}"
+    )]
+    CpuTimeLimitExceeded {
+        command: &'static str,
+        limit: Duration,
+    },
+    #[error("Barretenberg `{command}` command failed ({status}): {stderr}")]
+    CommandFailed {
+        command: &'static str,
+        status: ExitStatus,
+        stderr: String,
+    },
+    #[error("Barretenberg `{command}` command returned malformed output: {reason}")]
+    MalformedOutput {
+        command: &'static str,
+        reason: String,
+    },
+}
 
 const USERNAME: &str = "AztecProtocol";
 const REPO: &str = "barretenberg";
-const VERSION: &str = "0.4.6";
-const TAG: &str = formatcp!("barretenberg-v{}", VERSION);
+/// The barretenberg release which this crate targets.
+pub(crate) const VERSION: &str = "0.4.6";
+const TAG_PREFIX: &str = "barretenberg-v";
 const DEST_FOLDER: &str = ".nargo/backends/acvm-backend-barretenberg";
-const BINARY_NAME: &str = "backend_binary";
-
-const API_URL: &str = formatcp!(
-    "https://github.com/{}/{}/releases/download/{}",
-    USERNAME,
-    REPO,
-    TAG
-);
-
-fn get_bb_download_url() -> String {
-    if let Ok(path) = std::env::var("BB_BINARY_URL") {
-        return path;
+const BINARY_NAME: &str = "bb";
+
+/// Returns the name of the release archive containing the binary for the target platform.
+fn get_archive_name() -> Result<&'static str, CliShimError> {
+    archive_name_for(env!("TARGET_OS"), env!("TARGET_ARCH"))
+}
+
+/// Returns the name of the release archive containing the binary for `target_arch` and `target_os`.
+fn archive_name_for(
+    target_os: &'static str,
+    target_arch: &'static str,
+) -> Result<&'static str, CliShimError> {
+    match (target_os, target_arch) {
+        ("linux", "x86_64") => Ok("barretenberg-x86_64-linux-gnu.tar.gz"),
+        ("macos", "aarch64") => Ok("barretenberg-aarch64-apple-darwin.tar.gz"),
+        ("macos", "x86_64") => Ok("barretenberg-x86_64-apple-darwin.tar.gz"),
+        (os, arch) => Err(CliShimError::UnsupportedPlatform { os, arch }),
     }
+}
 
-    let target_os = env!("TARGET_OS");
-    let target_arch = env!("TARGET_ARCH");
-
-    let archive_name = match target_os {
-        "linux" => "barretenberg-x86_64-linux-gnu.tar.gz",
-        "macos" => match target_arch {
-            "aarch64" => "barretenberg-aarch64-apple-darwin.tar.gz",
-            "x86_64" => "barretenberg-x86_64-apple-darwin.tar.gz",
-            arch => panic!("unsupported arch {arch}"),
-        },
-        os => panic!("Unsupported OS {os}"),
-    };
+/// Returns the URL from which the barretenberg binary for `version` should be downloaded.
+///
+/// The URL for the version targeted by this crate may be overridden through `config`.
+/// Otherwise, the name of the release archive is also returned if its digest is pinned by this crate.
+fn get_bb_download_url(
+    config: &CliShimConfig,
+    version: &str,
+) -> Result<(String, Option<&'static str>), CliShimError> {
+    if version == VERSION {
+        if let Some(url) = &config.download_url {
+            return Ok((url.clone(), None));
+        }
+    }
 
-    format!("{API_URL}/{archive_name}")
+    let archive_name = get_archive_name()?;
+    let url = format!(
+        "https://github.com/{USERNAME}/{REPO}/releases/download/{TAG_PREFIX}{version}/{archive_name}"
+    );
+    let pinned_archive = (version == VERSION).then_some(archive_name);
+
+    Ok((url, pinned_archive))
 }
 
-/// Returns the path to the binary that was set by the `NARGO_BINARIES_PATH` environment variable
-fn get_binary_path() -> PathBuf {
-    match std::env::var("BB_BINARY_PATH") {
-        Ok(path) => PathBuf::from(path),
-        Err(_) => dirs::home_dir()
-            .unwrap()
-            .join(formatcp!("{}/{}", DEST_FOLDER, BINARY_NAME)),
+/// Returns the path to the barretenberg binary, downloading it first if necessary.
+///
+/// The binary is checked to belong to the barretenberg release targeted by this crate.
+fn assert_binary_exists(config: &CliShimConfig) -> Result<PathBuf, CliShimError> {
+    let binary_path = match &config.binary_path {
+        Some(binary_path) => {
+            if !binary_path.exists() {
+                install::with_install_lock(binary_path, || {
+                    // Another process may have installed the binary while we were waiting for the lock.
+                    if !binary_path.exists() {
+                        let manifest =
+                            install::download_bb_binary(config, VERSION, None, binary_path)?;
+                        cache::write_manifest(binary_path, &manifest)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            binary_path.clone()
+        }
+        None => cache::install_version(config, VERSION, None)?,
+    };
+
+    if !binary_path.exists() {
+        return Err(CliShimError::BinaryNotFound(binary_path));
     }
+
+    version::assert_compatible(&binary_path)?;
+    Ok(binary_path)
 }
 
-fn assert_binary_exists() {
-    if !get_binary_path().exists() {
-        download_bb_binary()
+/// Installs the barretenberg binary from a local release archive, extracted release directory
+/// or prebuilt `bb` executable, returning the path to which it was installed.
+pub(crate) fn install_binary(
+    config: &CliShimConfig,
+    source: &Path,
+) -> Result<PathBuf, CliShimError> {
+    match &config.binary_path {
+        Some(binary_path) => {
+            install::with_install_lock(binary_path, || {
+                install::install_from_path(config, VERSION, source, binary_path)
+            })?;
+            Ok(binary_path.clone())
+        }
+        None => cache::install_from_path(config, VERSION, source),
     }
 }
 
-fn download_bb_binary() {
-    use flate2::read::GzDecoder;
-    use tar::Archive;
-    use tempfile::tempdir;
+/// Downloads the barretenberg binary if necessary and checks that it is compatible with this crate.
+///
+/// Once this has succeeded, creating a command for the binary no longer downloads or probes it.
+#[cfg(feature = "async")]
+pub(crate) fn ensure_binary(config: &CliShimConfig) -> Result<(), CliShimError> {
+    assert_binary_exists(config).map(drop)
+}
 
-    // Create directory to place binary in.
-    std::fs::create_dir_all(get_binary_path().parent().unwrap()).unwrap();
+/// Returns the SHA-256 digest of the barretenberg binary, downloading it first if necessary.
+pub(crate) fn binary_digest(config: &CliShimConfig) -> Result<String, CliShimError> {
+    version::binary_digest(&assert_binary_exists(config)?)
+}
 
-    // Download sources
-    let compressed_file: Cursor<Vec<u8>> = download_binary_from_url(&get_bb_download_url())
-        .unwrap_or_else(|error| panic!("\n\nDownload error: {error}\n\n"));
+/// Returns the barretenberg binaries stored in the binary cache.
+pub(crate) fn cached_binaries(config: &CliShimConfig) -> Result<Vec<CachedBinary>, CliShimError> {
+    cache::list_cached_versions(config)
+}
 
-    // Unpack the tarball
-    let gz_decoder = GzDecoder::new(compressed_file);
-    let mut archive = Archive::new(gz_decoder);
+/// Downloads the barretenberg binary for `version` into the binary cache, returning its path.
+pub(crate) fn install_version(
+    config: &CliShimConfig,
+    version: &str,
+    archive_sha256: Option<&str>,
+) -> Result<PathBuf, CliShimError> {
+    cache::install_version(config, version, archive_sha256)
+}
 
-    let temp_directory = tempdir().expect("could not create a temporary directory");
-    archive.unpack(&temp_directory).unwrap();
-    let binary_path = temp_directory.path().join("bb");
+/// Removes cached binaries other than the version targeted by this crate and those listed in `keep`.
+pub(crate) fn prune_cached_binaries(
+    config: &CliShimConfig,
+    keep: &[&str],
+) -> Result<Vec<String>, CliShimError> {
+    let mut keep = keep.to_vec();
+    keep.push(VERSION);
+    cache::prune_cached_versions(config, &keep)
+}
 
-    // Rename the binary to the desired name
-    std::fs::copy(binary_path, get_binary_path()).unwrap();
+/// Executes `command` to completion and returns its output, regardless of the exit status.
+///
+/// `name` is the barretenberg subcommand being run and is only used for error reporting.
+/// If `stdin` is provided then it is written to the binary's stdin, otherwise stdin is closed.
+/// If `config` sets a timeout for the command then the binary is killed once it has been running for that long,
+/// as it is if the command is cancelled through `config`'s cancellation handle.
+/// If the binary is killed for exceeding the resource limits set by `config` then this is reported as an error.
+/// With the `experimental-workers` feature, if `config` enables worker mode then the command is sent to
+/// a long-lived barretenberg process instead.
+///
+/// Each line of output is parsed for progress reports as it is written and, with the `tracing` feature,
+/// logged within a span covering the command.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "bb",
+        skip_all,
+        err,
+        fields(
+            operation = name,
+            exit_status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        )
+    )
+)]
+fn execute(
+    config: &CliShimConfig,
+    name: &'static str,
+    mut command: Command,
+    stdin: Option<&[u8]>,
+) -> Result<Output, CliShimError> {
+    let deadline = Deadline::start(config, name)?;
+    let started = Instant::now();
 
-    drop(temp_directory);
+    // The CPU time limit applies to a worker's whole lifetime rather than to each command which it runs.
+    #[cfg(feature = "experimental-workers")]
+    if let (Some(workers), None) = (&config.workers, config.limits.cpu_time) {
+        if let Some(output) = workers.execute(config, &deadline, &command, stdin) {
+            // Workers respond once the command has finished, so its output can only be logged afterwards.
+            let output = output?;
+            OutputLines::new(config, name, "stdout", started).lines(&output.stdout);
+            OutputLines::new(config, name, "stderr", started).lines(&output.stderr);
+            return finish(config, name, started, output, None);
+        }
+    }
+
+    let spawn_error = |source| CliShimError::Spawn {
+        command: name,
+        source,
+    };
+
+    let mut child = command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    // The input is written on a separate thread, as the binary may not read it all before writing its output.
+    if let (Some(mut child_stdin), Some(stdin)) = (child.stdin.take(), stdin) {
+        let stdin = zeroize::Zeroizing::new(stdin.to_vec());
+        std::thread::spawn(move || {
+            // The binary may exit without reading its input, which is reported through its exit status.
+            let _ = child_stdin.write_all(&stdin);
+        });
+    }
+
+    // The pipes must be drained while waiting, otherwise the binary may block once they are full.
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| read_in_background(pipe, OutputLines::new(config, name, "stdout", started)));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| read_in_background(pipe, OutputLines::new(config, name, "stderr", started)));
+
+    // The child is waited on directly rather than through `Child`, so that the resources which it used are reported.
+    let (status, usage) = if deadline.is_unbounded() {
+        usage::wait(&child, true)
+            .map_err(spawn_error)?
+            .expect("blocking wait should return once the child has exited")
+    } else {
+        loop {
+            if let Some(exited) = usage::wait(&child, false).map_err(spawn_error)? {
+                break exited;
+            }
+
+            match deadline.check() {
+                Ok(poll_interval) => std::thread::sleep(poll_interval),
+                Err(error) => {
+                    cancellation::kill_child(&mut child);
+                    return Err(error);
+                }
+            }
+        }
+    };
+
+    let collect = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    let output = Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    };
+    finish(config, name, started, output, Some(usage))
+}
+
+/// Checks the `output` of the `name` command, which was started at `started`, once the binary has exited.
+///
+/// If `config` is recording a performance report then the command's running time and `usage` are added to it.
+fn finish(
+    config: &CliShimConfig,
+    name: &'static str,
+    started: Instant,
+    output: Output,
+    usage: Option<ResourceUsage>,
+) -> Result<Output, CliShimError> {
+    let wall_time = started.elapsed();
+    trace::record_exit(&output.status, wall_time);
+    if let (Some(recorder), Some(operation)) = (&config.recorder, Operation::from_command(name)) {
+        recorder.record_command(operation, wall_time, usage);
+    }
+    config.limits.check(name, output, usage)
 }
 
-/// Try to download the specified URL into a buffer which is returned.
-fn download_binary_from_url(url: &str) -> Result<Cursor<Vec<u8>>, String> {
-    let response = reqwest::blocking::get(url).map_err(|error| error.to_string())?;
+/// Reads `pipe` to completion on a separate thread, handling each line as it is read.
+fn read_in_background(
+    pipe: impl Read + Send + 'static,
+    mut lines: OutputLines,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut pipe = BufReader::new(pipe);
+        let mut contents = Vec::new();
+        loop {
+            let start = contents.len();
+            match pipe.read_until(b'\n', &mut contents) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => lines.line(&contents[start..]),
+            }
+        }
+        contents
+    })
+}
 
-    let bytes = response.bytes().unwrap();
+/// Executes `command`, returning its output if the barretenberg binary exited successfully.
+fn run_command(
+    config: &CliShimConfig,
+    name: &'static str,
+    command: Command,
+    stdin: Option<&[u8]>,
+) -> Result<Output, CliShimError> {
+    successful_output(name, execute(config, name, command, stdin)?)
+}
 
-    // TODO: Check SHA of downloaded binary
+/// Executes a command which reports a boolean result through its exit code, such as `verify`.
+///
+/// A non-zero exit code is interpreted as `false`, however the command is still considered
+/// to have failed if the binary was terminated by a signal.
+fn run_predicate_command(
+    config: &CliShimConfig,
+    name: &'static str,
+    command: Command,
+    stdin: Option<&[u8]>,
+) -> Result<bool, CliShimError> {
+    predicate_result(name, execute(config, name, command, stdin)?)
+}
 
-    Ok(Cursor::new(bytes.to_vec()))
+/// Returns the `output` of the `name` command if the barretenberg binary exited successfully.
+fn successful_output(name: &'static str, output: Output) -> Result<Output, CliShimError> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(command_failed(name, &output))
+    }
+}
+
+/// Interprets the exit code in the `output` of the `name` command as a boolean result.
+fn predicate_result(name: &'static str, output: Output) -> Result<bool, CliShimError> {
+    if output.status.code().is_none() {
+        return Err(command_failed(name, &output));
+    }
+    Ok(output.status.success())
+}
+
+fn command_failed(name: &'static str, output: &Output) -> CliShimError {
+    CliShimError::CommandFailed {
+        command: name,
+        status: output.status,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+/// Writes an executable script to `path` which stands in for the barretenberg binary in tests.
+#[cfg(test)]
+pub(crate) fn write_mock_binary(path: &Path, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::write(path, script).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
 }
 
 #[test]
 fn no_command_provided_works() {
     // This is a simple test to check that the binaries work
 
-    assert_binary_exists();
+    let binary_path = assert_binary_exists(&CliShimConfig::from_env()).unwrap();
 
-    let output = std::process::Command::new(get_binary_path())
+    let output = std::process::Command::new(binary_path)
         .output()
         .expect("Failed to execute command");
 
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert_eq!(stderr, "No command provided.\n");
 }
+
+#[test]
+fn unsupported_platforms_are_an_error() {
+    assert!(archive_name_for("macos", "aarch64").is_ok());
+    assert!(matches!(
+        archive_name_for("linux", "aarch64"),
+        Err(CliShimError::UnsupportedPlatform {
+            os: "linux",
+            arch: "aarch64"
+        })
+    ));
+    assert!(matches!(
+        archive_name_for("windows", "x86_64"),
+        Err(CliShimError::UnsupportedPlatform { .. })
+    ));
+}
+
+#[test]
+fn commands_are_killed_after_timeout() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    write_mock_binary(&binary_path, "#!/bin/sh\necho started\nsleep 10\n");
+
+    let config = CliShimConfig {
+        timeout: Some(Duration::from_millis(200)),
+        ..CliShimConfig::default()
+    };
+
+    let started = Instant::now();
+    let result = execute(&config, "prove", Command::new(&binary_path), None);
+    assert!(matches!(
+        result,
+        Err(CliShimError::Timeout {
+            command: "prove",
+            ..
+        })
+    ));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let config = CliShimConfig {
+        timeout: Some(Duration::from_secs(5)),
+        ..CliShimConfig::default()
+    };
+    write_mock_binary(&binary_path, "#!/bin/sh\necho finished\n");
+    let output = run_command(&config, "prove", Command::new(&binary_path), None).unwrap();
+    assert_eq!(output.stdout, b"finished\n");
+
+    // The timeout of an operation takes precedence over the default timeout.
+    let config = CliShimConfig {
+        timeout: Some(Duration::from_secs(5)),
+        operation_timeouts: [(Operation::Prove, Duration::from_millis(200))].into(),
+        ..CliShimConfig::default()
+    };
+    write_mock_binary(&binary_path, "#!/bin/sh\nsleep 10\n");
+    let result = execute(&config, "prove", Command::new(&binary_path), None);
+    assert!(matches!(
+        result,
+        Err(CliShimError::Timeout {
+            command: "prove",
+            timeout,
+        }) if timeout == Duration::from_millis(200)
+    ));
+    assert_eq!(config.timeout_for("verify"), Some(Duration::from_secs(5)));
+
+    drop(temp_directory);
+}
+
+#[test]
+fn cancelled_commands_are_killed_with_their_children() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    let marker_path = temp_directory.path().join("finished");
+    write_mock_binary(
+        &binary_path,
+        &format!(
+            "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\n(sleep 1; touch {}) &\nsleep 10\n",
+            marker_path.display()
+        ),
+    );
+
+    let cancellation = CancellationHandle::new();
+    let config = CliShimConfig {
+        binary_path: Some(binary_path),
+        cancellation: Some(cancellation.clone()),
+        ..CliShimConfig::default()
+    };
+    let command = config.command().unwrap();
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        cancellation.cancel();
+    });
+    let started = Instant::now();
+    let result = execute(&config, "prove", command, None);
+    assert!(matches!(
+        result,
+        Err(CliShimError::Cancelled { command: "prove" })
+    ));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    canceller.join().unwrap();
+
+    // The process started by the binary belongs to its process group, so should have been killed with it.
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(!marker_path.exists());
+
+    drop(temp_directory);
+}
@@ -0,0 +1,215 @@
+use std::{
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{CliShimConfig, Operation};
+use crate::trace::OutputLog;
+
+/// The messages which barretenberg logs when run verbosely, along with the phase which each of them announces.
+///
+/// Barretenberg also logs the number of gates once the circuit has been built, as `gates: <count>`,
+/// and `done.` once each phase has completed, neither of which begins a new phase.
+/// Messages are matched at the start of a line.
+const PHASE_MESSAGES: [(&str, ProgressPhase); 4] = [
+    ("building circuit", ProgressPhase::CircuitConstruction),
+    ("computing proving key", ProgressPhase::ProvingKey),
+    ("computing verification key", ProgressPhase::VerificationKey),
+    ("creating proof", ProgressPhase::Proof),
+];
+
+/// A phase of a long-running barretenberg operation, as reported by barretenberg itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProgressPhase {
+    /// Constructing the circuit from its ACIR bytecode.
+    CircuitConstruction,
+    /// Generating the proving key.
+    ProvingKey,
+    /// Generating the verification key.
+    VerificationKey,
+    /// Constructing a proof once the proving key is available.
+    ///
+    /// Barretenberg does not report its progress within a proof, such as computing commitments
+    /// or the opening proof, so there are no phases for these and this is the last phase of creating a proof.
+    Proof,
+}
+
+impl ProgressPhase {
+    /// Returns the phase announced by a line logged by barretenberg, if any.
+    fn from_message(line: &str) -> Option<ProgressPhase> {
+        PHASE_MESSAGES
+            .iter()
+            .find(|(message, _)| line.starts_with(message))
+            .map(|(_, phase)| *phase)
+    }
+}
+
+/// Reports that barretenberg has begun a new phase of an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressEvent {
+    /// The operation which barretenberg is performing.
+    pub operation: Operation,
+    /// The phase which barretenberg has begun.
+    pub phase: ProgressPhase,
+    /// When the phase began.
+    pub timestamp: SystemTime,
+    /// How long the operation had been running when the phase began.
+    pub elapsed: Duration,
+}
+
+/// A callback which is notified of each [`ProgressEvent`], shared between clones of the configuration.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Arc<dyn Fn(ProgressEvent) + Send + Sync>);
+
+impl ProgressCallback {
+    pub(crate) fn new(
+        callback: impl Fn(ProgressEvent) + Send + Sync + 'static,
+    ) -> ProgressCallback {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    /// Creates a callback which sends each event over `sender`.
+    ///
+    /// Events are discarded once the receiver has been dropped.
+    pub(crate) fn channel(sender: Sender<ProgressEvent>) -> ProgressCallback {
+        let sender = Mutex::new(sender);
+        ProgressCallback::new(move |event| {
+            if let Ok(sender) = sender.lock() {
+                let _ = sender.send(event);
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressCallback").finish()
+    }
+}
+
+/// Handles each line which barretenberg writes to one of its output streams as it is read,
+/// tracking the phase which barretenberg has reached and logging the line.
+pub(super) struct OutputLines {
+    operation: Option<Operation>,
+    started: Instant,
+    callback: Option<ProgressCallback>,
+    phase: Option<ProgressPhase>,
+    log: OutputLog,
+}
+
+impl OutputLines {
+    /// Creates a handler for the `stream` written by the `name` command, which was started at `started`.
+    pub(super) fn new(
+        config: &CliShimConfig,
+        name: &'static str,
+        stream: &'static str,
+        started: Instant,
+    ) -> OutputLines {
+        OutputLines {
+            operation: Operation::from_command(name),
+            started,
+            callback: config.progress.clone(),
+            phase: None,
+            log: OutputLog::new(name, stream),
+        }
+    }
+
+    /// Handles a single `line` of output, including its terminating newline.
+    pub(super) fn line(&mut self, line: &[u8]) {
+        // Output which is not text, such as a gate count or proof written to stdout, is neither parsed nor logged.
+        let Ok(line) = std::str::from_utf8(line) else {
+            return;
+        };
+        let line = line.trim_end();
+        if line.is_empty() || line.chars().any(|c| c.is_control() && c != '\t') {
+            return;
+        }
+
+        if let Some(phase) = ProgressPhase::from_message(line) {
+            if self.phase != Some(phase) {
+                self.phase = Some(phase);
+                if let (Some(callback), Some(operation)) = (&self.callback, self.operation) {
+                    (callback.0)(ProgressEvent {
+                        operation,
+                        phase,
+                        timestamp: SystemTime::now(),
+                        elapsed: self.started.elapsed(),
+                    });
+                }
+            }
+        }
+        self.log.line(line, self.phase);
+    }
+
+    /// Handles every line of `output` which has already been read in full.
+    #[cfg(feature = "experimental-workers")]
+    pub(super) fn lines(mut self, output: &[u8]) {
+        for line in output.split_inclusive(|&byte| byte == b'\n') {
+            self.line(line);
+        }
+    }
+}
+
+#[test]
+fn phases_are_reported_as_they_begin() {
+    use std::sync::mpsc;
+
+    use tempfile::tempdir;
+
+    // The output of `bb prove -v` for the `1_mul` circuit.
+    const PROVE_OUTPUT: &str = "\
+building circuit...
+gates: 2775
+computing proving key...
+done.
+creating proof...
+done.
+";
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    super::write_mock_binary(
+        &binary_path,
+        &format!(
+            r#"#!/bin/sh
+[ "$1" = --version ] && echo 0.4.6 && exit 0
+printf '%s' '{PROVE_OUTPUT}' >&2
+printf '\001\000\000\000\000\000\000\000'
+"#
+        ),
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    let config = CliShimConfig {
+        binary_path: Some(binary_path),
+        progress: Some(ProgressCallback::channel(sender)),
+        ..CliShimConfig::default()
+    };
+    let output = super::run_command(&config, "prove", config.command().unwrap(), None).unwrap();
+    assert_eq!(output.stdout, [1, 0, 0, 0, 0, 0, 0, 0]);
+    drop(config);
+
+    let events: Vec<ProgressEvent> = receiver.iter().collect();
+    let phases: Vec<ProgressPhase> = events.iter().map(|event| event.phase).collect();
+    assert_eq!(
+        phases,
+        [
+            ProgressPhase::CircuitConstruction,
+            ProgressPhase::ProvingKey,
+            ProgressPhase::Proof,
+        ]
+    );
+    assert!(events
+        .iter()
+        .all(|event| event.operation == Operation::Prove));
+    assert!(events
+        .windows(2)
+        .all(|pair| pair[0].elapsed <= pair[1].elapsed));
+
+    assert_eq!(
+        ProgressPhase::from_message("computing verification key..."),
+        Some(ProgressPhase::VerificationKey)
+    );
+
+    drop(temp_directory);
+}
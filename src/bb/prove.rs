@@ -1,36 +1,53 @@
-use super::{assert_binary_exists, get_binary_path, CliShimError};
+use std::process::Command;
+
+use super::{run_command, CliShimConfig, CliShimError, InputFile, OutputFile};
 
 /// ProveCommand will call the barretenberg binary
 /// to create a proof, given the witness and the bytecode.
 ///
-/// Note:Internally barretenberg will create and discard the
-/// proving key, so this is not returned.
+/// Note: Barretenberg will internally create and discard the proving key.
+/// `bb prove` has no option for loading a proving key, as its `-k` option is the path of a verification key.
 ///
-/// The proof will be written to the specified output file.
+/// The proof will be written to the specified output file and returned.
 pub(crate) struct ProveCommand {
     pub(crate) verbose: bool,
     pub(crate) path_to_crs: String,
     pub(crate) is_recursive: bool,
     pub(crate) path_to_bytecode: String,
-    pub(crate) path_to_witness: String,
-    pub(crate) path_to_proof: String,
+    pub(crate) witness: InputFile,
+    pub(crate) proof: OutputFile,
 }
 
 impl ProveCommand {
-    pub(crate) fn run(self) -> Result<(), CliShimError> {
-        assert_binary_exists();
-        let mut command = std::process::Command::new(get_binary_path());
+    pub(crate) fn run(self, config: &CliShimConfig) -> Result<Vec<u8>, CliShimError> {
+        let command = self.command(config)?;
+        let output = run_command(config, "prove", command, self.witness.stdin())?;
+        self.proof.read("prove", output)
+    }
+
+    /// Runs the command without blocking, killing barretenberg if the returned future is dropped.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async(self, config: &CliShimConfig) -> Result<Vec<u8>, CliShimError> {
+        let command = self.command(config)?;
+        let output =
+            super::async_process::run_command_async(config, "prove", command, self.witness.stdin())
+                .await?;
+        self.proof.read("prove", output)
+    }
+
+    fn command(&self, config: &CliShimConfig) -> Result<Command, CliShimError> {
+        let mut command = config.command()?;
 
         command
             .arg("prove")
             .arg("-c")
-            .arg(self.path_to_crs)
+            .arg(&self.path_to_crs)
             .arg("-b")
-            .arg(self.path_to_bytecode)
+            .arg(&self.path_to_bytecode)
             .arg("-w")
-            .arg(self.path_to_witness)
+            .arg(self.witness.path())
             .arg("-o")
-            .arg(self.path_to_proof);
+            .arg(self.proof.path());
 
         if self.verbose {
             command.arg("-v");
@@ -39,13 +56,7 @@ impl ProveCommand {
             command.arg("-r");
         }
 
-        let output = command.output().expect("Failed to execute command");
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(CliShimError(String::from_utf8(output.stderr).unwrap()))
-        }
+        Ok(command)
     }
 }
 
@@ -56,6 +67,7 @@ fn prove_command() {
     let path_to_1_mul = "./src/1_mul.bytecode";
     let path_to_1_mul_witness = "./src/witness.tr";
 
+    let config = CliShimConfig::from_env();
     let temp_directory = tempdir().expect("could not create a temporary directory");
     let temp_directory_path = temp_directory.path();
 
@@ -67,11 +79,61 @@ fn prove_command() {
         path_to_crs: path_to_crs.to_str().unwrap().to_string(),
         is_recursive: false,
         path_to_bytecode: path_to_1_mul.to_string(),
-        path_to_witness: path_to_1_mul_witness.to_string(),
-        path_to_proof: path_to_proof.to_str().unwrap().to_string(),
+        witness: InputFile::Path(path_to_1_mul_witness.to_string()),
+        proof: OutputFile::Path(path_to_proof.to_str().unwrap().to_string()),
     };
 
-    let proof_created = prove_command.run();
+    let proof_created = prove_command.run(&config);
     assert!(proof_created.is_ok());
     drop(temp_directory);
 }
+
+#[test]
+fn prove_command_arguments() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    let arguments_path = temp_directory.path().join("arguments");
+    crate::bb::write_mock_binary(
+        &binary_path,
+        &format!(
+            r#"#!/bin/sh
+[ "$1" = --version ] && echo 0.4.6 && exit 0
+echo "$@" > {arguments}
+printf proof > proof
+"#,
+            arguments = arguments_path.display()
+        ),
+    );
+
+    let config = CliShimConfig {
+        binary_path: Some(binary_path),
+        ..CliShimConfig::default()
+    };
+    let prove_command = ProveCommand {
+        verbose: true,
+        path_to_crs: "crs".to_string(),
+        is_recursive: true,
+        path_to_bytecode: "circuit.bytecode".to_string(),
+        witness: InputFile::Path("witness.tr".to_string()),
+        proof: OutputFile::Path(temp_directory.path().join("proof").display().to_string()),
+    };
+    let mut command = prove_command.command(&config).unwrap();
+    assert!(command
+        .current_dir(temp_directory.path())
+        .status()
+        .unwrap()
+        .success());
+
+    let arguments = std::fs::read_to_string(&arguments_path).unwrap();
+    assert_eq!(
+        arguments.trim_end(),
+        format!(
+            "prove -c crs -b circuit.bytecode -w witness.tr -o {} -v -r",
+            temp_directory.path().join("proof").display()
+        )
+    );
+
+    drop(temp_directory);
+}
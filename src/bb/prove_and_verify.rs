@@ -1,4 +1,4 @@
-use super::{assert_binary_exists, get_binary_path};
+use super::{run_predicate_command, CliShimConfig, CliShimError};
 
 /// ProveAndVerifyCommand will call the barretenberg binary
 /// to create a proof and then verify the proof once created.
@@ -16,9 +16,8 @@ struct ProveAndVerifyCommand {
 
 #[allow(dead_code)]
 impl ProveAndVerifyCommand {
-    fn run(self) -> bool {
-        assert_binary_exists();
-        let mut command = std::process::Command::new(get_binary_path());
+    fn run(self, config: &CliShimConfig) -> Result<bool, CliShimError> {
+        let mut command = config.command()?;
 
         command
             .arg("prove_and_verify")
@@ -35,11 +34,7 @@ impl ProveAndVerifyCommand {
             command.arg("-r");
         }
 
-        command
-            .output()
-            .expect("Failed to execute command")
-            .status
-            .success()
+        run_predicate_command(config, "prove_and_verify", command, None)
     }
 }
 
@@ -50,6 +45,7 @@ fn prove_and_verify_command() {
     let path_to_1_mul = "./src/1_mul.bytecode";
     let path_to_1_mul_witness = "./src/witness.tr";
 
+    let config = CliShimConfig::from_env();
     let temp_directory = tempdir().expect("could not create a temporary directory");
     let temp_directory_path = temp_directory.path();
     let path_to_crs = temp_directory_path.join("crs");
@@ -62,7 +58,7 @@ fn prove_and_verify_command() {
         path_to_witness: path_to_1_mul_witness.to_string(),
     };
 
-    let output = prove_and_verify_command.run();
+    let output = prove_and_verify_command.run(&config).unwrap();
     assert!(output);
     drop(temp_directory);
 }
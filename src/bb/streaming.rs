@@ -0,0 +1,148 @@
+use std::{collections::BTreeMap, process::Output, sync::Mutex, time::Duration};
+
+use zeroize::Zeroizing;
+
+use super::{assert_binary_exists, execute, version, CliShimConfig, CliShimError};
+
+/// The path through which barretenberg reads an input which is streamed to it over stdin.
+const STDIN_PATH: &str = "/dev/stdin";
+/// The path through which barretenberg writes an output which is streamed back over stdout.
+const STDOUT_PATH: &str = "/dev/stdout";
+
+/// How long the probe may run before the binary is assumed to be unable to stream.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether each binary which has already been probed by this process can stream its inputs and outputs,
+/// indexed by the binary's SHA-256 digest.
+static PROBED_BINARIES: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+/// A file which barretenberg reads, which may instead be streamed to it over stdin.
+///
+/// Streamed contents are wiped from memory once they are dropped, as they may include a witness.
+#[derive(Debug)]
+pub(crate) enum InputFile {
+    Path(String),
+    Stdin(Zeroizing<Vec<u8>>),
+}
+
+impl InputFile {
+    /// Returns the path which barretenberg should read the input from.
+    pub(super) fn path(&self) -> &str {
+        match self {
+            InputFile::Path(path) => path,
+            InputFile::Stdin(_) => STDIN_PATH,
+        }
+    }
+
+    /// Returns the contents which must be written to barretenberg's stdin, if any.
+    pub(super) fn stdin(&self) -> Option<&[u8]> {
+        match self {
+            InputFile::Path(_) => None,
+            InputFile::Stdin(contents) => Some(contents),
+        }
+    }
+}
+
+/// A file which barretenberg writes, which may instead be streamed back over stdout.
+#[derive(Debug)]
+pub(crate) enum OutputFile {
+    Path(String),
+    Stdout,
+}
+
+impl OutputFile {
+    /// Returns the path which barretenberg should write the output to.
+    pub(super) fn path(&self) -> &str {
+        match self {
+            OutputFile::Path(path) => path,
+            OutputFile::Stdout => STDOUT_PATH,
+        }
+    }
+
+    /// Returns the contents written by the `name` command which produced `output`.
+    pub(super) fn read(self, name: &'static str, output: Output) -> Result<Vec<u8>, CliShimError> {
+        match self {
+            OutputFile::Path(path) => {
+                std::fs::read(&path).map_err(|error| CliShimError::MalformedOutput {
+                    command: name,
+                    reason: format!("could not read {path}: {error}"),
+                })
+            }
+            OutputFile::Stdout => Ok(output.stdout),
+        }
+    }
+}
+
+/// Checks whether the barretenberg binary can read its inputs from stdin and write its outputs to stdout.
+///
+/// The binary is probed by counting the gates of `probe_bytecode` read from stdin,
+/// which only happens the first time that the binary is seen by this process.
+/// The probe is not one of the caller's operations, so it is run without the timeouts, cancellation,
+/// progress reporting, performance report or workers configured for them.
+pub(crate) fn supports_streaming(
+    config: &CliShimConfig,
+    probe_bytecode: &[u8],
+) -> Result<bool, CliShimError> {
+    let binary_path = assert_binary_exists(config)?;
+    let binary_sha256 = version::binary_digest(&binary_path)?;
+    let probed = PROBED_BINARIES
+        .lock()
+        .ok()
+        .and_then(|probed| probed.get(&binary_sha256).copied());
+    if let Some(supported) = probed {
+        return Ok(supported);
+    }
+
+    let probe_config = CliShimConfig {
+        binary_path: Some(binary_path),
+        timeout: Some(PROBE_TIMEOUT),
+        ..CliShimConfig::default()
+    };
+    let mut command = probe_config.command()?;
+    command.arg("gates").arg("-b").arg(STDIN_PATH);
+    // Older releases of barretenberg determine the size of their inputs by seeking, which fails on a pipe.
+    let supported = match execute(&probe_config, "gates", command, Some(probe_bytecode)) {
+        Ok(output) => output.status.success() && output.stdout.len() == 8,
+        Err(_) => false,
+    };
+
+    if let Ok(mut probed) = PROBED_BINARIES.lock() {
+        probed.insert(binary_sha256, supported);
+    }
+    Ok(supported)
+}
+
+#[test]
+fn streaming_support_is_probed() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+
+    let streaming_path = temp_directory.path().join("streaming_bb");
+    super::write_mock_binary(
+        &streaming_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\n[ \"$(cat \"$3\")\" = bytecode ] || exit 1\nprintf '\\001\\000\\000\\000\\000\\000\\000\\000'\n",
+    );
+    // The probe is not subject to the cancellation of the operations which it is run for.
+    let cancellation = super::CancellationHandle::new();
+    cancellation.cancel();
+    let config = CliShimConfig {
+        binary_path: Some(streaming_path),
+        cancellation: Some(cancellation),
+        ..CliShimConfig::default()
+    };
+    assert!(supports_streaming(&config, b"bytecode").unwrap());
+
+    let seeking_path = temp_directory.path().join("seeking_bb");
+    super::write_mock_binary(
+        &seeking_path,
+        "#!/bin/sh\n[ \"$1\" = --version ] && echo 0.4.6 && exit 0\necho 'Unable to read file' >&2\nexit 1\n",
+    );
+    let config = CliShimConfig {
+        binary_path: Some(seeking_path),
+        ..CliShimConfig::default()
+    };
+    assert!(!supports_streaming(&config, b"bytecode").unwrap());
+
+    drop(temp_directory);
+}
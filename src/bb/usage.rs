@@ -0,0 +1,84 @@
+use std::{
+    io,
+    os::unix::process::ExitStatusExt,
+    process::{Child, ExitStatus},
+    time::Duration,
+};
+
+/// The number of bytes in each unit in which the kernel reports peak resident set size.
+#[cfg(target_os = "macos")]
+const MAX_RSS_UNIT: u64 = 1;
+#[cfg(not(target_os = "macos"))]
+const MAX_RSS_UNIT: u64 = 1024;
+
+/// The resources which a barretenberg process consumed over its lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ResourceUsage {
+    pub(crate) peak_rss_bytes: u64,
+    pub(crate) user_cpu_time: Duration,
+    pub(crate) system_cpu_time: Duration,
+}
+
+impl ResourceUsage {
+    fn from_rusage(rusage: &libc::rusage) -> ResourceUsage {
+        let duration = |time: libc::timeval| {
+            Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+        };
+        ResourceUsage {
+            peak_rss_bytes: rusage.ru_maxrss as u64 * MAX_RSS_UNIT,
+            user_cpu_time: duration(rusage.ru_utime),
+            system_cpu_time: duration(rusage.ru_stime),
+        }
+    }
+}
+
+/// Waits for `child` to exit, returning its exit status along with the resources which it consumed.
+///
+/// If `block` is false then `None` is returned immediately if the child is still running.
+/// Once its status has been returned, the child has been reaped and must not be waited on or killed again.
+pub(super) fn wait(child: &Child, block: bool) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    let pid = libc::pid_t::try_from(child.id())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let options = if block { 0 } else { libc::WNOHANG };
+
+    loop {
+        let mut status = 0;
+        // SAFETY: `rusage` is a plain C struct, for which all zeros is a valid value.
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: `status` and `rusage` are valid for writes for the duration of the call.
+        match unsafe { libc::wait4(pid, &mut status, options, &mut rusage) } {
+            0 => return Ok(None),
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            _ => {
+                return Ok(Some((
+                    ExitStatus::from_raw(status),
+                    ResourceUsage::from_rusage(&rusage),
+                )))
+            }
+        }
+    }
+}
+
+#[test]
+fn resource_usage_is_reported() {
+    use std::process::Command;
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg("i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done; exit 3")
+        .spawn()
+        .unwrap();
+
+    let (status, usage) = wait(&child, true).unwrap().unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert!(usage.peak_rss_bytes > 0);
+    assert!(usage.user_cpu_time + usage.system_cpu_time > Duration::ZERO);
+
+    // The child has already been reaped, so it cannot be waited on again.
+    assert!(child.wait().is_err());
+}
@@ -1,4 +1,6 @@
-use super::{assert_binary_exists, get_binary_path};
+use std::process::Command;
+
+use super::{run_predicate_command, CliShimConfig, CliShimError, InputFile};
 
 /// VerifyCommand will call the barretenberg binary
 /// to verify a proof
@@ -6,23 +8,40 @@ pub(crate) struct VerifyCommand {
     pub(crate) verbose: bool,
     pub(crate) path_to_crs: String,
     pub(crate) is_recursive: bool,
-    pub(crate) path_to_proof: String,
+    pub(crate) proof: InputFile,
     pub(crate) path_to_vk: String,
 }
 
 impl VerifyCommand {
-    pub(crate) fn run(self) -> bool {
-        assert_binary_exists();
-        let mut command = std::process::Command::new(get_binary_path());
+    pub(crate) fn run(self, config: &CliShimConfig) -> Result<bool, CliShimError> {
+        let command = self.command(config)?;
+        run_predicate_command(config, "verify", command, self.proof.stdin())
+    }
+
+    /// Runs the command without blocking, killing barretenberg if the returned future is dropped.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async(self, config: &CliShimConfig) -> Result<bool, CliShimError> {
+        let command = self.command(config)?;
+        super::async_process::run_predicate_command_async(
+            config,
+            "verify",
+            command,
+            self.proof.stdin(),
+        )
+        .await
+    }
+
+    fn command(&self, config: &CliShimConfig) -> Result<Command, CliShimError> {
+        let mut command = config.command()?;
 
         command
             .arg("verify")
             .arg("-c")
-            .arg(self.path_to_crs)
+            .arg(&self.path_to_crs)
             .arg("-p")
-            .arg(self.path_to_proof)
+            .arg(self.proof.path())
             .arg("-k")
-            .arg(self.path_to_vk);
+            .arg(&self.path_to_vk);
 
         if self.verbose {
             command.arg("-v");
@@ -31,8 +50,7 @@ impl VerifyCommand {
             command.arg("-r");
         }
 
-        let output = command.output().expect("Failed to execute command");
-        output.status.success()
+        Ok(command)
     }
 }
 
@@ -40,11 +58,12 @@ impl VerifyCommand {
 fn verify_command() {
     use tempfile::tempdir;
 
-    use crate::bb::{ProveCommand, WriteVkCommand};
+    use crate::bb::{OutputFile, ProveCommand, WriteVkCommand};
 
     let path_to_1_mul = "./src/1_mul.bytecode";
     let path_to_1_mul_witness = "./src/witness.tr";
 
+    let config = CliShimConfig::from_env();
     let temp_directory = tempdir().expect("could not create a temporary directory");
     let temp_directory_path = temp_directory.path();
 
@@ -60,7 +79,7 @@ fn verify_command() {
         path_to_vk_output: path_to_vk.to_str().unwrap().to_string(),
     };
 
-    let vk_written = write_vk_command.run();
+    let vk_written = write_vk_command.run(&config);
     assert!(vk_written.is_ok());
 
     let prove_command = ProveCommand {
@@ -68,20 +87,20 @@ fn verify_command() {
         path_to_crs: path_to_crs.to_str().unwrap().to_string(),
         is_recursive: false,
         path_to_bytecode: path_to_1_mul.to_string(),
-        path_to_witness: path_to_1_mul_witness.to_string(),
-        path_to_proof: path_to_proof.to_str().unwrap().to_string(),
+        witness: InputFile::Path(path_to_1_mul_witness.to_string()),
+        proof: OutputFile::Path(path_to_proof.to_str().unwrap().to_string()),
     };
-    prove_command.run().unwrap();
+    prove_command.run(&config).unwrap();
 
     let verify_command = VerifyCommand {
         verbose: true,
         path_to_crs: path_to_crs.to_str().unwrap().to_string(),
         is_recursive: false,
-        path_to_proof: path_to_proof.to_str().unwrap().to_string(),
+        proof: InputFile::Path(path_to_proof.to_str().unwrap().to_string()),
         path_to_vk: path_to_vk.to_str().unwrap().to_string(),
     };
 
-    let verified = verify_command.run();
+    let verified = verify_command.run(&config).unwrap();
     assert!(verified);
     drop(temp_directory);
 }
@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::{
+    cache,
+    digest::{sha256_hex, verify_digest},
+    install, CliShimError, VERSION,
+};
+
+/// The versions of each binary which have already been probed by this process.
+///
+/// A version of `None` means that the binary was recognised as barretenberg but its version could not be determined.
+static PROBED_VERSIONS: Mutex<BTreeMap<PathBuf, Option<String>>> = Mutex::new(BTreeMap::new());
+
+/// The SHA-256 digests of each binary which has already been identified by this process.
+static BINARY_DIGESTS: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
+/// Checks that the binary at `binary_path` belongs to the barretenberg release targeted by this crate.
+///
+/// The binary is only probed the first time that it is seen by this process.
+pub(super) fn assert_compatible(binary_path: &Path) -> Result<(), CliShimError> {
+    let cached_version = PROBED_VERSIONS
+        .lock()
+        .ok()
+        .and_then(|probed| probed.get(binary_path).cloned());
+
+    let version = match cached_version {
+        Some(version) => version,
+        None => {
+            let version = detect_version(binary_path)?;
+            if let Ok(mut probed) = PROBED_VERSIONS.lock() {
+                probed.insert(binary_path.to_path_buf(), version.clone());
+            }
+            version
+        }
+    };
+
+    match version {
+        Some(version) => check_version(binary_path, VERSION, &version),
+        None => Err(CliShimError::UnknownVersion {
+            path: binary_path.to_path_buf(),
+            expected: VERSION.to_string(),
+        }),
+    }
+}
+
+/// Returns the SHA-256 digest of the binary at `binary_path`, which is hashed the first time that it is seen by this process.
+///
+/// If the binary has a manifest then it must match the digest recorded there, so that a binary which has been
+/// replaced since it was installed is not mistaken for the one which was.
+pub(super) fn binary_digest(binary_path: &Path) -> Result<String, CliShimError> {
+    let cached_digest = BINARY_DIGESTS
+        .lock()
+        .ok()
+        .and_then(|digests| digests.get(binary_path).cloned());
+    if let Some(digest) = cached_digest {
+        return Ok(digest);
+    }
+
+    let binary = std::fs::read(binary_path).map_err(|error| CliShimError::InvalidBinary {
+        path: binary_path.to_path_buf(),
+        reason: format!("could not be read: {error}"),
+    })?;
+    if let Some(manifest) = cache::read_manifest(binary_path) {
+        verify_digest("executable", &binary, &manifest.sha256)?;
+    }
+    let digest = sha256_hex(&binary);
+    if let Ok(mut digests) = BINARY_DIGESTS.lock() {
+        digests.insert(binary_path.to_path_buf(), digest.clone());
+    }
+    Ok(digest)
+}
+
+/// Forgets the result of probing `binary_path`, for instance because a new binary has been installed there.
+pub(super) fn forget_probe(binary_path: &Path) {
+    if let Ok(mut probed) = PROBED_VERSIONS.lock() {
+        probed.remove(binary_path);
+    }
+    if let Ok(mut digests) = BINARY_DIGESTS.lock() {
+        digests.remove(binary_path);
+    }
+}
+
+/// Checks that the `found` version of the binary at `binary_path` matches the `expected` version.
+pub(super) fn check_version(
+    binary_path: &Path,
+    expected: &str,
+    found: &str,
+) -> Result<(), CliShimError> {
+    if found == expected {
+        Ok(())
+    } else {
+        Err(CliShimError::IncompatibleBinary {
+            path: binary_path.to_path_buf(),
+            expected: expected.to_string(),
+            found: found.to_string(),
+        })
+    }
+}
+
+/// Determines the barretenberg release which the binary at `binary_path` belongs to.
+///
+/// Returns `None` if the binary is a working barretenberg binary but its version could not be determined.
+pub(super) fn detect_version(binary_path: &Path) -> Result<Option<String>, CliShimError> {
+    let output = std::process::Command::new(binary_path)
+        .arg("--version")
+        .output()
+        .map_err(|source| CliShimError::Spawn {
+            command: "--version",
+            source,
+        })?;
+
+    if output.status.success() {
+        if let Some(version) = parse_version(&String::from_utf8_lossy(&output.stdout)) {
+            return Ok(Some(version));
+        }
+    }
+
+    // Older releases of barretenberg cannot report their version, so instead we check that the binary
+    // responds like barretenberg and rely on the manifest recorded when it was installed, if there is one.
+    install::probe_binary(binary_path)?;
+    Ok(cache::read_manifest(binary_path).map(|manifest| manifest.version))
+}
+
+/// Extracts a semantic version, such as `0.4.6`, from the output of `bb --version`.
+fn parse_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .map(|word| {
+            word.trim_start_matches("barretenberg-")
+                .trim_start_matches('v')
+        })
+        .find(|word| {
+            let parts: Vec<&str> = word.split('.').collect();
+            parts.len() == 3
+                && parts
+                    .iter()
+                    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(str::to_string)
+}
+
+#[test]
+fn version_parsing() {
+    assert_eq!(parse_version("0.4.6\n").as_deref(), Some("0.4.6"));
+    assert_eq!(parse_version("bb v0.7.10").as_deref(), Some("0.7.10"));
+    assert_eq!(
+        parse_version("barretenberg-v0.5.1").as_deref(),
+        Some("0.5.1")
+    );
+    assert_eq!(parse_version("Unknown command: --version"), None);
+}
+
+#[test]
+fn incompatible_binary_is_rejected() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    super::write_mock_binary(&binary_path, "#!/bin/sh\necho 0.1.0\n");
+
+    let result = assert_compatible(&binary_path);
+    assert!(matches!(
+        result,
+        Err(CliShimError::IncompatibleBinary { ref found, .. }) if found == "0.1.0"
+    ));
+
+    drop(temp_directory);
+}
+
+#[test]
+fn binary_digest_is_checked_against_its_manifest() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    super::write_mock_binary(&binary_path, install::MOCK_BB_SCRIPT);
+    let manifest = |sha256: String| cache::BinaryManifest {
+        version: VERSION.to_string(),
+        source: "test".to_string(),
+        sha256,
+    };
+
+    let digest = sha256_hex(install::MOCK_BB_SCRIPT.as_bytes());
+    cache::write_manifest(&binary_path, &manifest(digest.clone())).unwrap();
+    assert_eq!(binary_digest(&binary_path).unwrap(), digest);
+
+    // A binary which was replaced after its manifest was written is not identified by the manifest.
+    forget_probe(&binary_path);
+    super::write_mock_binary(&binary_path, "#!/bin/sh\necho 0.4.6\n");
+    assert!(matches!(
+        binary_digest(&binary_path),
+        Err(CliShimError::DigestMismatch { .. })
+    ));
+
+    drop(temp_directory);
+}
+
+#[test]
+fn binary_of_unknown_version_is_rejected() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    super::write_mock_binary(&binary_path, install::MOCK_BB_SCRIPT);
+
+    // Without a manifest recording its version, a binary which cannot report its version is not trusted.
+    let result = assert_compatible(&binary_path);
+    assert!(matches!(result, Err(CliShimError::UnknownVersion { .. })));
+
+    drop(temp_directory);
+}
@@ -0,0 +1,130 @@
+use acvm::FieldElement;
+
+use super::{run_command, CliShimConfig, CliShimError};
+
+/// VkAsFieldsCommand will call the barretenberg binary
+/// to convert a verification key into the field elements
+/// consumed by barretenberg's recursive verifier.
+///
+/// The hash of the verification key is written to the output file
+/// as the first element, followed by the verification key's fields.
+pub(crate) struct VkAsFieldsCommand {
+    pub(crate) verbose: bool,
+    pub(crate) path_to_crs: String,
+    pub(crate) path_to_vk: String,
+    pub(crate) path_to_vk_as_fields: String,
+}
+
+impl VkAsFieldsCommand {
+    pub(crate) fn run(
+        self,
+        config: &CliShimConfig,
+    ) -> Result<(Vec<FieldElement>, FieldElement), CliShimError> {
+        let mut command = config.command()?;
+
+        command
+            .arg("vk_as_fields")
+            .arg("-c")
+            .arg(self.path_to_crs)
+            .arg("-k")
+            .arg(self.path_to_vk)
+            .arg("-o")
+            .arg(&self.path_to_vk_as_fields);
+
+        if self.verbose {
+            command.arg("-v");
+        }
+
+        run_command(config, "vk_as_fields", command, None)?;
+
+        let output = std::fs::read(&self.path_to_vk_as_fields).map_err(|error| {
+            CliShimError::MalformedOutput {
+                command: "vk_as_fields",
+                reason: format!("could not read {}: {error}", self.path_to_vk_as_fields),
+            }
+        })?;
+        parse_vk_fields(&output)
+    }
+}
+
+/// Parses the JSON array of hex-encoded field elements written by the `vk_as_fields` command.
+fn parse_vk_fields(output: &[u8]) -> Result<(Vec<FieldElement>, FieldElement), CliShimError> {
+    let malformed_output = |reason: String| CliShimError::MalformedOutput {
+        command: "vk_as_fields",
+        reason,
+    };
+
+    let encoded_fields: Vec<String> =
+        serde_json::from_slice(output).map_err(|error| malformed_output(error.to_string()))?;
+    let mut fields = encoded_fields
+        .iter()
+        .map(|field| {
+            FieldElement::from_hex(field)
+                .ok_or_else(|| malformed_output(format!("{field} is not a hex-encoded field")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if fields.is_empty() {
+        return Err(malformed_output(
+            "expected the verification key hash".to_string(),
+        ));
+    }
+    let vk_hash = fields.remove(0);
+
+    Ok((fields, vk_hash))
+}
+
+#[test]
+fn vk_as_fields_command() {
+    use tempfile::tempdir;
+
+    let path_to_1_mul = "./src/1_mul.bytecode";
+
+    let config = CliShimConfig::from_env();
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let temp_directory_path = temp_directory.path();
+    let path_to_crs = temp_directory_path.join("crs");
+    let path_to_vk = temp_directory_path.join("vk");
+    let path_to_vk_as_fields = temp_directory_path.join("vk_as_fields");
+
+    let write_vk_command = super::WriteVkCommand {
+        verbose: true,
+        path_to_bytecode: path_to_1_mul.to_string(),
+        path_to_crs: path_to_crs.to_str().unwrap().to_string(),
+        is_recursive: true,
+        path_to_vk_output: path_to_vk.to_str().unwrap().to_string(),
+    };
+    write_vk_command.run(&config).unwrap();
+
+    let vk_as_fields_command = VkAsFieldsCommand {
+        verbose: true,
+        path_to_crs: path_to_crs.to_str().unwrap().to_string(),
+        path_to_vk: path_to_vk.to_str().unwrap().to_string(),
+        path_to_vk_as_fields: path_to_vk_as_fields.to_str().unwrap().to_string(),
+    };
+
+    let (vk_fields, _vk_hash) = vk_as_fields_command.run(&config).unwrap();
+    assert!(!vk_fields.is_empty());
+    drop(temp_directory);
+}
+
+#[test]
+fn vk_fields_output_is_parsed() {
+    let output = br#"["0x0000000000000000000000000000000000000000000000000000000000000007", "0x01", "0x02"]"#;
+    let (vk_fields, vk_hash) = parse_vk_fields(output).unwrap();
+    assert_eq!(vk_hash, FieldElement::from(7_u128));
+    assert_eq!(
+        vk_fields,
+        vec![FieldElement::from(1_u128), FieldElement::from(2_u128)]
+    );
+
+    for malformed_output in [&b"[]"[..], b"[\"0xzz\"]", b"not json"] {
+        assert!(matches!(
+            parse_vk_fields(malformed_output),
+            Err(CliShimError::MalformedOutput {
+                command: "vk_as_fields",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Output, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use super::{cancellation, CliShimConfig, CliShimError, Deadline};
+
+/// The subcommand which starts the barretenberg binary as a worker.
+const WORKER_COMMAND: &str = "worker";
+/// The line which a worker writes to stdout once it is ready to accept requests.
+const HANDSHAKE: &str = "bb-worker 1";
+/// How long a newly started worker has to complete the handshake before worker mode is assumed to be unsupported.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether each binary which has already been probed by this process completed the worker handshake.
+static PROBED_BINARIES: Mutex<BTreeMap<PathBuf, bool>> = Mutex::new(BTreeMap::new());
+
+/// A pool of long-lived barretenberg processes which commands are sent to, rather than spawning a process per command.
+///
+/// Worker mode is experimental: no barretenberg release provides a `bb worker` subcommand yet, so this protocol
+/// is only spoken by binaries built to support it. A worker is started by running `bb worker`, after which it writes the line `bb-worker 1` to stdout.
+/// Each request is then written to its stdin as a header line holding the length in bytes of the request
+/// and the length of the command's stdin separated by a space, followed by the arguments which would otherwise
+/// have been passed to the binary, encoded as a JSON array of strings, and then the contents of the command's stdin.
+/// The worker replies on stdout with a header line holding the exit code, the length of stdout and the length
+/// of stderr separated by spaces, followed by the contents of stdout and then stderr. A negative exit code
+/// indicates that the command was terminated by the signal with that number.
+///
+/// Workers which crash are replaced the next time a command is run. Each binary is checked to support worker mode
+/// by completing the handshake the first time that it is seen by this process. A binary which does not is never
+/// started as a worker again, and every command is run in a process of its own.
+#[derive(Debug)]
+pub(crate) struct WorkerPool {
+    /// The maximum number of idle workers which are kept running.
+    size: usize,
+    idle: Mutex<Vec<Worker>>,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(size: usize) -> WorkerPool {
+        WorkerPool {
+            size,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Runs `command` on a worker, returning `None` if it should instead be run in a process of its own.
+    ///
+    /// The worker is killed if the command is still running once `deadline` passes.
+    pub(super) fn execute(
+        &self,
+        config: &CliShimConfig,
+        deadline: &Deadline<'_>,
+        command: &Command,
+        stdin: Option<&[u8]>,
+    ) -> Option<Result<Output, CliShimError>> {
+        let binary_path = PathBuf::from(command.get_program());
+        if supports_workers(&binary_path) == Some(false) {
+            return None;
+        }
+
+        let worker = self.acquire(config);
+        if let Ok(worker) = &worker {
+            if let Ok(mut probed) = PROBED_BINARIES.lock() {
+                probed.insert(binary_path, worker.is_some());
+            }
+        }
+        let mut worker = match worker {
+            Ok(Some(worker)) => worker,
+            Ok(None) => return None,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let args: Vec<String> = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        match worker.request(&args, stdin.unwrap_or_default(), deadline) {
+            Ok(output) => {
+                self.release(worker);
+                Some(Ok(output))
+            }
+            // The worker is killed when it is dropped, so its replacement will be started by the next command.
+            Err(WorkerFailure::Stopped(error)) => Some(Err(error)),
+            // Running the command in a process of its own reports the error which caused the worker to crash.
+            Err(WorkerFailure::Crashed) => None,
+        }
+    }
+
+    /// Takes an idle worker from the pool, starting a new one if there are none.
+    ///
+    /// Returns `None` if the binary does not support worker mode.
+    fn acquire(&self, config: &CliShimConfig) -> Result<Option<Worker>, CliShimError> {
+        let idle_worker = self.idle.lock().ok().and_then(|mut idle| idle.pop());
+        match idle_worker {
+            Some(worker) => Ok(Some(worker)),
+            None => Worker::start(config),
+        }
+    }
+
+    /// Returns `worker` to the pool, unless the pool already holds as many idle workers as it may.
+    fn release(&self, worker: Worker) {
+        if let Ok(mut idle) = self.idle.lock() {
+            if idle.len() < self.size {
+                idle.push(worker);
+            }
+        }
+    }
+}
+
+/// Returns whether the binary at `binary_path` completed the worker handshake, if it has been probed.
+fn supports_workers(binary_path: &Path) -> Option<bool> {
+    PROBED_BINARIES
+        .lock()
+        .ok()
+        .and_then(|probed| probed.get(binary_path).copied())
+}
+
+/// A message written to stdout by a worker.
+#[derive(Debug)]
+enum Frame {
+    Ready,
+    Response(Output),
+}
+
+/// The reasons for which a worker may fail to respond to a request.
+#[derive(Debug)]
+enum WorkerFailure {
+    /// The request timed out or was cancelled.
+    Stopped(CliShimError),
+    Crashed,
+}
+
+/// A running barretenberg worker, which is killed when dropped.
+#[derive(Debug)]
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    frames: Receiver<Frame>,
+}
+
+impl Worker {
+    /// Starts a worker, returning `None` if it does not complete the handshake.
+    fn start(config: &CliShimConfig) -> Result<Option<Worker>, CliShimError> {
+        let mut child = config
+            .command()?
+            .arg(WORKER_COMMAND)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|source| CliShimError::Spawn {
+                command: WORKER_COMMAND,
+                source,
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin of worker should be piped");
+        let stdout = child
+            .stdout
+            .take()
+            .expect("stdout of worker should be piped");
+        let worker = Worker {
+            child,
+            stdin,
+            frames: read_frames(stdout),
+        };
+
+        match worker.frames.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(Frame::Ready) => Ok(Some(worker)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Sends a request to run barretenberg with `args` and `stdin`, then waits for the response.
+    fn request(
+        &mut self,
+        args: &[String],
+        stdin: &[u8],
+        deadline: &Deadline<'_>,
+    ) -> Result<Output, WorkerFailure> {
+        let request = serde_json::to_vec(args).expect("arguments should serialize to JSON");
+        writeln!(self.stdin, "{} {}", request.len(), stdin.len())
+            .and_then(|_| self.stdin.write_all(&request))
+            .and_then(|_| self.stdin.write_all(stdin))
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| WorkerFailure::Crashed)?;
+
+        let frame = if deadline.is_unbounded() {
+            self.frames.recv().map_err(|_| WorkerFailure::Crashed)?
+        } else {
+            loop {
+                let poll_interval = deadline.check().map_err(WorkerFailure::Stopped)?;
+                match self.frames.recv_timeout(poll_interval) {
+                    Ok(frame) => break frame,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return Err(WorkerFailure::Crashed),
+                }
+            }
+        };
+
+        match frame {
+            Frame::Response(output) => Ok(output),
+            Frame::Ready => Err(WorkerFailure::Crashed),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        cancellation::kill_child(&mut self.child);
+    }
+}
+
+/// Reads the frames written by a worker to `stdout` on a separate thread.
+///
+/// The returned channel is disconnected once the worker exits or writes a malformed frame.
+fn read_frames(stdout: ChildStdout) -> Receiver<Frame> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdout = BufReader::new(stdout);
+        let mut handshake = String::new();
+        if stdout.read_line(&mut handshake).is_err() || handshake.trim_end() != HANDSHAKE {
+            return;
+        }
+        if sender.send(Frame::Ready).is_err() {
+            return;
+        }
+
+        while let Some(output) = read_response(&mut stdout) {
+            if sender.send(Frame::Response(output)).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+/// Reads a single response from a worker, returning `None` if it is malformed or the worker has exited.
+fn read_response(stdout: &mut impl BufRead) -> Option<Output> {
+    let mut header = String::new();
+    stdout.read_line(&mut header).ok()?;
+
+    let mut fields = header.split_whitespace();
+    let exit_code: i32 = fields.next()?.parse().ok()?;
+    let stdout_length: usize = fields.next()?.parse().ok()?;
+    let stderr_length: usize = fields.next()?.parse().ok()?;
+
+    let mut read_exact = |length: usize| {
+        let mut contents = vec![0; length];
+        stdout.read_exact(&mut contents).ok().map(|_| contents)
+    };
+    let output_stdout = read_exact(stdout_length)?;
+    let output_stderr = read_exact(stderr_length)?;
+
+    Some(Output {
+        status: exit_status(exit_code),
+        stdout: output_stdout,
+        stderr: output_stderr,
+    })
+}
+
+/// Converts an exit code reported by a worker into the status of a process which exited with that code.
+fn exit_status(exit_code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    if exit_code < 0 {
+        ExitStatus::from_raw(-exit_code)
+    } else {
+        ExitStatus::from_raw((exit_code & 0xff) << 8)
+    }
+}
+
+/// A mock barretenberg binary which supports worker mode, identifying the process which handled each request.
+#[cfg(test)]
+const MOCK_WORKER_SCRIPT: &str = r#"#!/bin/sh
+case "$1" in
+    --version) echo 0.4.6 ;;
+    worker)
+        echo "bb-worker 1"
+        while read length stdin_length; do
+            request=$(head -c "$length")
+            head -c "$stdin_length" > /dev/null
+            response="$$ $request"
+            printf '0 %d 0\n%s' "${#response}" "$response"
+            [ -n "$EXIT_AFTER_REQUEST" ] && exit 0
+        done ;;
+    *) echo "process $1" ;;
+esac
+"#;
+
+#[cfg(test)]
+fn mock_worker_config(binary_path: &std::path::Path, script: &str) -> CliShimConfig {
+    super::write_mock_binary(binary_path, script);
+    CliShimConfig {
+        binary_path: Some(binary_path.to_path_buf()),
+        workers: Some(std::sync::Arc::new(WorkerPool::new(1))),
+        ..CliShimConfig::default()
+    }
+}
+
+#[cfg(test)]
+fn run_gates(config: &CliShimConfig) -> String {
+    let mut command = config.command().unwrap();
+    command.arg("gates").arg("-b").arg("circuit.bytecode");
+    let output = super::run_command(config, "gates", command, None).unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn commands_are_sent_to_long_lived_workers() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let config = mock_worker_config(&temp_directory.path().join("bb"), MOCK_WORKER_SCRIPT);
+
+    let first = run_gates(&config);
+    let (first_worker, request) = first.split_once(' ').unwrap();
+    assert_eq!(request, r#"["gates","-b","circuit.bytecode"]"#);
+
+    let second = run_gates(&config);
+    let (second_worker, _) = second.split_once(' ').unwrap();
+    assert_eq!(first_worker, second_worker);
+
+    drop(temp_directory);
+}
+
+#[test]
+fn crashed_workers_are_restarted() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let script = MOCK_WORKER_SCRIPT.replace("#!/bin/sh\n", "#!/bin/sh\nEXIT_AFTER_REQUEST=1\n");
+    let config = mock_worker_config(&temp_directory.path().join("bb"), &script);
+
+    let first = run_gates(&config);
+    let (first_worker, _) = first.split_once(' ').unwrap();
+
+    // The worker exits after its first request, so the next command must be run in a process of its own.
+    assert_eq!(run_gates(&config), "process gates\n");
+
+    let third = run_gates(&config);
+    let (third_worker, _) = third.split_once(' ').unwrap();
+    assert_ne!(first_worker, third_worker);
+
+    drop(temp_directory);
+}
+
+#[test]
+fn binaries_without_worker_mode_run_a_process_per_command() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let script = MOCK_WORKER_SCRIPT.replace(
+        "    worker)\n",
+        "    worker)\n        echo 'Unknown command: worker' >&2\n        exit 1\n",
+    );
+    let binary_path = temp_directory.path().join("bb");
+    let config = mock_worker_config(&binary_path, &script);
+
+    assert_eq!(run_gates(&config), "process gates\n");
+    assert_eq!(supports_workers(&binary_path), Some(false));
+
+    // Other backends using the same binary do not try to start it as a worker again.
+    let config = CliShimConfig {
+        workers: Some(std::sync::Arc::new(WorkerPool::new(1))),
+        ..config
+    };
+    assert_eq!(run_gates(&config), "process gates\n");
+
+    drop(temp_directory);
+}
@@ -1,4 +1,4 @@
-use super::{assert_binary_exists, get_binary_path, CliShimError};
+use super::{run_command, CliShimConfig, CliShimError};
 
 /// WriteCommand will call the barretenberg binary
 /// to write a verification key to a file
@@ -11,9 +11,8 @@ pub(crate) struct WriteVkCommand {
 }
 
 impl WriteVkCommand {
-    pub(crate) fn run(self) -> Result<(), CliShimError> {
-        assert_binary_exists();
-        let mut command = std::process::Command::new(get_binary_path());
+    pub(crate) fn run(self, config: &CliShimConfig) -> Result<(), CliShimError> {
+        let mut command = config.command()?;
 
         command
             .arg("write_vk")
@@ -31,13 +30,8 @@ impl WriteVkCommand {
             command.arg("-r");
         }
 
-        let output = command.output().expect("Failed to execute command");
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(CliShimError(String::from_utf8(output.stderr).unwrap()))
-        }
+        run_command(config, "write_vk", command, None)?;
+        Ok(())
     }
 }
 
@@ -47,6 +41,7 @@ fn write_vk_command() {
 
     let path_to_1_mul = "./src/1_mul.bytecode";
 
+    let config = CliShimConfig::from_env();
     let temp_directory = tempdir().expect("could not create a temporary directory");
     let temp_directory_path = temp_directory.path();
     let path_to_crs = temp_directory_path.join("crs");
@@ -60,7 +55,7 @@ fn write_vk_command() {
         path_to_vk_output: path_to_vk.to_str().unwrap().to_string(),
     };
 
-    let vk_written = write_vk_command.run();
+    let vk_written = write_vk_command.run(&config);
     assert!(vk_written.is_ok());
     drop(temp_directory);
 }
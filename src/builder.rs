@@ -0,0 +1,287 @@
+use std::{path::PathBuf, sync::mpsc::Sender, time::Duration};
+
+#[cfg(feature = "experimental-workers")]
+use std::sync::Arc;
+
+#[cfg(feature = "experimental-workers")]
+use crate::bb::WorkerPool;
+use crate::{
+    artifacts::{self, ArtifactCache},
+    bb::{CliShimConfig, ProgressCallback},
+    Barretenberg, CancellationHandle, Operation, ProgressEvent,
+};
+
+/// Builds a [`Barretenberg`] backend with its own configuration.
+///
+/// Any settings which are not provided fall back to the environment variables read by [`Barretenberg::new`]:
+/// `BB_BINARY_PATH`, `BB_BINARY_URL`, `BB_BINARY_SHA256`, `BB_EXECUTABLE_SHA256` and `BB_OFFLINE`.
+/// This allows several backends using different binaries or CRS directories to coexist in one process.
+#[derive(Debug, Clone)]
+pub struct BarretenbergBuilder {
+    config: CliShimConfig,
+    crs_dir: Option<PathBuf>,
+    temp_dir: Option<PathBuf>,
+    witness_dir: Option<PathBuf>,
+    regenerate_verification_key: bool,
+    artifact_cache: bool,
+    artifact_cache_dir: Option<PathBuf>,
+    artifact_cache_size: u64,
+}
+
+impl Default for BarretenbergBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BarretenbergBuilder {
+    /// Creates a builder whose defaults are read from the environment.
+    pub fn new() -> BarretenbergBuilder {
+        BarretenbergBuilder {
+            config: CliShimConfig::from_env(),
+            crs_dir: None,
+            temp_dir: None,
+            witness_dir: None,
+            regenerate_verification_key: false,
+            artifact_cache: true,
+            artifact_cache_dir: None,
+            artifact_cache_size: artifacts::DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Sets the path of the barretenberg binary, which is downloaded to this path if it does not exist.
+    ///
+    /// By default, the binary is stored in the binary cache.
+    pub fn binary_path(mut self, binary_path: impl Into<PathBuf>) -> Self {
+        self.config.binary_path = Some(binary_path.into());
+        self
+    }
+
+    /// Sets the URL from which the barretenberg release archive is downloaded, along with its expected SHA-256 digest.
+    pub fn download_url(mut self, url: impl Into<String>, sha256: impl Into<String>) -> Self {
+        self.config.download_url = Some(url.into());
+        self.config.archive_sha256 = Some(sha256.into());
+        self
+    }
+
+    /// Sets the directory under which the binaries for each barretenberg release are cached.
+    ///
+    /// This defaults to `~/.nargo/backends/acvm-backend-barretenberg`.
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.config.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Sets the directory in which barretenberg stores the common reference string.
+    ///
+    /// This defaults to `~/.nargo/backends/acvm-backend-barretenberg/crs`.
+    pub fn crs_dir(mut self, crs_dir: impl Into<PathBuf>) -> Self {
+        self.crs_dir = Some(crs_dir.into());
+        self
+    }
+
+    /// Sets the directory in which temporary files passed to barretenberg are created.
+    ///
+    /// This defaults to the system's temporary directory.
+    pub fn temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    /// Sets the directory in which the files holding witnesses are created, such as a `tmpfs` mount.
+    ///
+    /// These files may only be read by the current user and are overwritten before they are removed.
+    /// This defaults to the directory in which other temporary files are created.
+    pub fn witness_dir(mut self, witness_dir: impl Into<PathBuf>) -> Self {
+        self.witness_dir = Some(witness_dir.into());
+        self
+    }
+
+    /// Sets whether `verify_with_vk` may regenerate the verification key from the circuit when none is provided.
+    ///
+    /// This is disabled by default, so that proofs are only verified against keys which the caller trusts.
+    /// `eth_contract_from_vk` always regenerates a missing key, as the generated contract is the verifier.
+    pub fn regenerate_verification_key(mut self, regenerate_verification_key: bool) -> Self {
+        self.regenerate_verification_key = regenerate_verification_key;
+        self
+    }
+
+    /// Sets whether the gate counts, keys and contracts generated for each circuit are cached.
+    ///
+    /// Caching is enabled by default, so that repeated operations on the same circuit are fast.
+    pub fn artifact_cache(mut self, enabled: bool) -> Self {
+        self.artifact_cache = enabled;
+        self
+    }
+
+    /// Sets the directory in which the artifacts generated for each circuit are cached.
+    ///
+    /// This defaults to `~/.nargo/backends/acvm-backend-barretenberg/artifacts`.
+    pub fn artifact_cache_dir(mut self, artifact_cache_dir: impl Into<PathBuf>) -> Self {
+        self.artifact_cache_dir = Some(artifact_cache_dir.into());
+        self
+    }
+
+    /// Sets the size in bytes beyond which the least recently used artifacts are evicted from the cache.
+    ///
+    /// This defaults to 2 GiB.
+    pub fn artifact_cache_size(mut self, max_size: u64) -> Self {
+        self.artifact_cache_size = max_size;
+        self
+    }
+
+    /// Sets whether downloading the barretenberg binary is forbidden.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.config.offline = offline;
+        self
+    }
+
+    /// Sets whether barretenberg should log its progress.
+    ///
+    /// By default barretenberg is only verbose while creating proofs.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.config.verbose = Some(verbose);
+        self
+    }
+
+    /// Calls `callback` each time barretenberg begins a new phase of an operation, such as computing the proving key
+    /// or creating the proof itself, so that the progress of long-running proofs can be displayed.
+    ///
+    /// Barretenberg only reports its progress when it is run verbosely, which this enables. The callback is called
+    /// from a background thread while barretenberg is running, so should return quickly.
+    ///
+    /// Progress within a proof, such as computing commitments or the opening proof, is not reported,
+    /// as barretenberg does not log it. [`ProgressPhase::Proof`](crate::ProgressPhase::Proof) is therefore the last event of a proof.
+    pub fn progress(mut self, callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        self.config.progress = Some(ProgressCallback::new(callback));
+        self.config.verbose = Some(true);
+        self
+    }
+
+    /// Sends a [`ProgressEvent`] over `sender` each time barretenberg begins a new phase of an operation,
+    /// as [`Self::progress`] does for a callback.
+    pub fn progress_channel(mut self, sender: Sender<ProgressEvent>) -> Self {
+        self.config.progress = Some(ProgressCallback::channel(sender));
+        self.config.verbose = Some(true);
+        self
+    }
+
+    /// Limits the number of threads which barretenberg may use.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = Some(threads);
+        self
+    }
+
+    /// Limits the virtual memory of each barretenberg process to `bytes`.
+    ///
+    /// A command which fails to allocate memory beyond this limit fails with an error for which
+    /// [`crate::BackendError::is_limit_exceeded`] returns `true`, rather than exhausting the memory of the machine.
+    pub fn memory_limit(mut self, bytes: u64) -> Self {
+        self.config.limits.memory = Some(bytes);
+        self
+    }
+
+    /// Limits the CPU time, summed across all threads and rounded up to a whole second,
+    /// which each barretenberg process may consume before it is killed.
+    ///
+    /// Unlike [`Self::timeout`], this does not count time spent waiting for the CPU on a busy machine.
+    /// Worker mode is not used while a CPU time limit is set, as the limit would apply to the lifetime of each worker.
+    pub fn cpu_time_limit(mut self, cpu_time: Duration) -> Self {
+        self.config.limits.cpu_time = Some(cpu_time);
+        self
+    }
+
+    /// Sets the maximum amount of time for which a single barretenberg command may run before it is killed.
+    ///
+    /// This applies to every operation which is not given a timeout of its own through [`Self::operation_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum amount of time for which barretenberg may spend performing `operation` before it is killed.
+    pub fn operation_timeout(mut self, operation: Operation, timeout: Duration) -> Self {
+        self.config.operation_timeouts.insert(operation, timeout);
+        self
+    }
+
+    /// Sets the handle through which the barretenberg commands run by the backend can be cancelled.
+    ///
+    /// See [`Barretenberg::with_cancellation`] for cancelling the commands run for a single request.
+    pub fn cancellation(mut self, cancellation: CancellationHandle) -> Self {
+        self.config.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Keeps up to `workers` barretenberg processes running between commands, rather than spawning one per command.
+    ///
+    /// This is experimental, so requires the `experimental-workers` feature: worker mode relies on
+    /// a `bb worker` subcommand which no barretenberg release provides yet.
+    /// The binary is checked to support worker mode the first time that it is used, and if it does not then each
+    /// command is run in a process of its own, as it is when `workers` is zero, which is the default.
+    #[cfg(feature = "experimental-workers")]
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.config.workers = (workers > 0).then(|| Arc::new(WorkerPool::new(workers)));
+        self
+    }
+
+    /// Sets whether witnesses, proofs and other data are streamed to barretenberg over stdin and read back over stdout,
+    /// rather than being written to temporary files.
+    ///
+    /// This keeps witnesses, which may contain secrets, from ever being written to disk. If the binary cannot read
+    /// its inputs from a pipe, which is checked the first time that it is used, then temporary files are used instead.
+    pub fn stream_io(mut self, stream_io: bool) -> Self {
+        self.config.stream_io = stream_io;
+        self
+    }
+
+    /// Creates a backend with the provided configuration.
+    pub fn build(self) -> Barretenberg {
+        let artifact_cache_dir = self
+            .artifact_cache_dir
+            .or_else(|| dirs::home_dir().map(|home| home.join(artifacts::DEFAULT_ARTIFACT_FOLDER)));
+        let artifact_cache = artifact_cache_dir
+            .filter(|_| self.artifact_cache)
+            .map(|directory| ArtifactCache::new(directory, self.artifact_cache_size));
+
+        Barretenberg {
+            config: self.config,
+            crs_dir: self.crs_dir,
+            temp_dir: self.temp_dir,
+            witness_dir: self.witness_dir,
+            regenerate_verification_key: self.regenerate_verification_key,
+            artifact_cache,
+        }
+    }
+}
+
+#[test]
+fn backends_use_their_own_cache() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let temp_directory_path = temp_directory.path();
+
+    let source_path = temp_directory_path.join("bb");
+    std::fs::write(
+        &source_path,
+        "#!/bin/sh\necho 'No command provided.' >&2\nexit 1\n",
+    )
+    .unwrap();
+
+    let first = Barretenberg::builder()
+        .cache_dir(temp_directory_path.join("first"))
+        .offline(true)
+        .build();
+    let second = Barretenberg::builder()
+        .cache_dir(temp_directory_path.join("second"))
+        .offline(true)
+        .build();
+
+    let installed_path = first.install_binary(&source_path).unwrap();
+    assert!(installed_path.starts_with(temp_directory_path.join("first")));
+    assert_eq!(first.cached_binaries().unwrap().len(), 1);
+    assert!(second.cached_binaries().unwrap().is_empty());
+
+    drop(temp_directory);
+}
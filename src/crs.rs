@@ -0,0 +1,244 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::Error;
+
+/// The directory within the user's home directory in which the common reference string is stored by default.
+pub(crate) const DEFAULT_CRS_FOLDER: &str = ".nargo/backends/acvm-backend-barretenberg/crs";
+
+/// The number of bytes used to store a point on G1.
+const G1_POINT_BYTES: usize = 64;
+/// The number of bytes used to store a point on G2.
+const G2_POINT_BYTES: usize = 128;
+
+/// Name of the file in which barretenberg records how many G1 points are stored.
+const SIZE_FILE: &str = "size";
+/// Name of the file in which barretenberg stores the G1 points.
+const G1_FILE: &str = "g1.dat";
+/// Name of the file in which barretenberg stores the G2 point.
+const G2_FILE: &str = "g2.dat";
+/// Name of the file which is locked while the store is being written to.
+const LOCK_FILE: &str = ".lock";
+
+/// Describes how much of the common reference string needed by a circuit is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrsStatus {
+    /// The number of G1 points stored in the CRS directory.
+    pub points_present: u32,
+    /// The number of G1 points which barretenberg requires to prove the circuit.
+    pub points_required: u32,
+    /// Whether the G2 point is stored in the CRS directory.
+    pub g2_present: bool,
+}
+
+impl CrsStatus {
+    /// Returns whether barretenberg can use the CRS directory without downloading any more points.
+    pub fn is_sufficient(&self) -> bool {
+        self.g2_present && self.points_present >= self.points_required
+    }
+}
+
+/// A directory holding the common reference string in the layout which barretenberg reads and writes.
+///
+/// The directory is shared between calls so that barretenberg only needs to download the points once.
+/// It contains a `size` file holding the number of G1 points, `g1.dat` holding the G1 points
+/// and `g2.dat` holding the G2 point.
+#[derive(Debug)]
+pub(crate) struct CrsStore {
+    directory: PathBuf,
+}
+
+impl CrsStore {
+    pub(crate) fn new(directory: PathBuf) -> CrsStore {
+        CrsStore { directory }
+    }
+
+    pub(crate) fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the number of G1 points available to barretenberg.
+    pub(crate) fn points_present(&self) -> u32 {
+        let recorded_size = std::fs::read_to_string(self.directory.join(SIZE_FILE))
+            .ok()
+            .and_then(|size| size.trim().parse::<u32>().ok())
+            .unwrap_or(0);
+
+        // Don't trust the size file if the points have not been completely written.
+        let stored_points = std::fs::metadata(self.directory.join(G1_FILE))
+            .map_or(0, |metadata| metadata.len() / G1_POINT_BYTES as u64);
+
+        recorded_size.min(u32::try_from(stored_points).unwrap_or(u32::MAX))
+    }
+
+    /// Returns whether the G2 point is available to barretenberg.
+    pub(crate) fn g2_present(&self) -> bool {
+        std::fs::metadata(self.directory.join(G2_FILE))
+            .map_or(false, |metadata| metadata.len() >= G2_POINT_BYTES as u64)
+    }
+
+    /// Reports how many points are available compared to the `points_required` by a circuit.
+    pub(crate) fn status(&self, points_required: u32) -> CrsStatus {
+        CrsStatus {
+            points_present: self.points_present(),
+            points_required,
+            g2_present: self.g2_present(),
+        }
+    }
+
+    /// Writes a common reference string supplied by the caller into the store.
+    ///
+    /// `common_reference_string` is expected to be the G1 points followed by the G2 point.
+    /// The store is left untouched if it already contains the supplied points.
+    pub(crate) fn materialize(&self, common_reference_string: &[u8]) -> Result<(), Error> {
+        let (g1_data, g2_data) = split_crs(common_reference_string)?;
+        let num_points = u32::try_from(g1_data.len() / G1_POINT_BYTES)
+            .map_err(|_| Error::InvalidCrs(common_reference_string.len()))?;
+
+        self.with_lock(|| {
+            if self.contains(g1_data, g2_data) {
+                return Ok(());
+            }
+
+            // The size is written last so that barretenberg never reads a size which the points don't match.
+            self.write_atomically(G1_FILE, g1_data)?;
+            self.write_atomically(G2_FILE, g2_data)?;
+            self.write_atomically(SIZE_FILE, num_points.to_string().as_bytes())
+        })
+    }
+
+    /// Returns whether the store already begins with the points in `g1_data` and holds the point in `g2_data`.
+    fn contains(&self, g1_data: &[u8], g2_data: &[u8]) -> bool {
+        let points_present = self.points_present() as usize;
+        if points_present * G1_POINT_BYTES < g1_data.len() {
+            return false;
+        }
+
+        let stored_g1 = std::fs::read(self.directory.join(G1_FILE)).unwrap_or_default();
+        let stored_g2 = std::fs::read(self.directory.join(G2_FILE)).unwrap_or_default();
+        stored_g1.starts_with(g1_data) && stored_g2.starts_with(g2_data)
+    }
+
+    fn write_atomically(&self, file_name: &str, contents: &[u8]) -> Result<(), Error> {
+        let path = self.directory.join(file_name);
+        let write_error = |source| Error::WriteFile {
+            path: path.clone(),
+            source,
+        };
+
+        let mut temp_file =
+            tempfile::NamedTempFile::new_in(&self.directory).map_err(write_error)?;
+        temp_file.write_all(contents).map_err(write_error)?;
+        temp_file.as_file().sync_all().map_err(write_error)?;
+        temp_file
+            .persist(&path)
+            .map_err(|error| write_error(error.error))?;
+        Ok(())
+    }
+
+    /// Runs `write` while holding an exclusive lock on the store.
+    fn with_lock(&self, write: impl FnOnce() -> Result<(), Error>) -> Result<(), Error> {
+        use fs2::FileExt;
+
+        let lock_path = self.directory.join(LOCK_FILE);
+        let lock_error = |source| Error::WriteFile {
+            path: lock_path.clone(),
+            source,
+        };
+
+        std::fs::create_dir_all(&self.directory).map_err(lock_error)?;
+        let lock_file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(lock_error)?;
+        lock_file.lock_exclusive().map_err(lock_error)?;
+
+        let result = write();
+
+        // The lock is also released when the file is closed, so a failure to unlock is not an error.
+        let _ = FileExt::unlock(&lock_file);
+        result
+    }
+}
+
+/// Returns the number of gates to which barretenberg pads a circuit with `num_gates` gates,
+/// which is the circuit size recorded in its verification key.
+pub(crate) fn padded_size(num_gates: u32) -> u32 {
+    num_gates.max(1).next_power_of_two()
+}
+
+/// Returns the number of G1 points which barretenberg loads for a circuit with `num_gates` gates.
+///
+/// Barretenberg requires one point more than the padded size of the circuit.
+pub(crate) fn points_required(num_gates: u32) -> u32 {
+    padded_size(num_gates) + 1
+}
+
+/// Splits a common reference string into its G1 points and G2 point.
+fn split_crs(common_reference_string: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let invalid_crs = || Error::InvalidCrs(common_reference_string.len());
+
+    let g1_length = common_reference_string
+        .len()
+        .checked_sub(G2_POINT_BYTES)
+        .ok_or_else(invalid_crs)?;
+    if g1_length == 0 || g1_length % G1_POINT_BYTES != 0 {
+        return Err(invalid_crs());
+    }
+
+    Ok(common_reference_string.split_at(g1_length))
+}
+
+#[test]
+fn supplied_crs_is_materialized() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let store = CrsStore::new(temp_directory.path().join("crs"));
+    assert_eq!(
+        store.status(5),
+        CrsStatus {
+            points_present: 0,
+            points_required: 5,
+            g2_present: false
+        }
+    );
+
+    let common_reference_string: Vec<u8> = (0..G1_POINT_BYTES * 8 + G2_POINT_BYTES)
+        .map(|byte| byte as u8)
+        .collect();
+    store.materialize(&common_reference_string).unwrap();
+
+    let status = store.status(5);
+    assert_eq!(status.points_present, 8);
+    assert!(status.is_sufficient());
+    assert!(!store.status(9).is_sufficient());
+    assert_eq!(
+        std::fs::read(store.directory().join(G2_FILE)).unwrap(),
+        &common_reference_string[G1_POINT_BYTES * 8..]
+    );
+
+    // A prefix of the stored points should not shrink the store.
+    let mut prefix = common_reference_string[..G1_POINT_BYTES * 2].to_vec();
+    prefix.extend_from_slice(&common_reference_string[G1_POINT_BYTES * 8..]);
+    store.materialize(&prefix).unwrap();
+    assert_eq!(store.points_present(), 8);
+
+    assert!(matches!(
+        store.materialize(&common_reference_string[1..]),
+        Err(Error::InvalidCrs(_))
+    ));
+
+    drop(temp_directory);
+}
+
+#[test]
+fn required_points_are_padded() {
+    assert_eq!(points_required(2775), 4097);
+    assert_eq!(points_required(4096), 4097);
+    assert_eq!(points_required(0), 2);
+}
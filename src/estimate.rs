@@ -0,0 +1,437 @@
+//! Estimation of the size of a circuit without running barretenberg, by modelling how barretenberg's
+//! UltraPlonk circuit builder lays out the gates for each opcode.
+//!
+//! Arithmetic and range constraints are modelled on the builder closely enough that the estimate for a circuit
+//! built from them, such as `1_mul.bytecode`, is within [`ESTIMATE_TOLERANCE`] of the size which `bb gates` reports.
+//! Memory opcodes are modelled less precisely. Other black box functions are charged rough per-call costs, which
+//! are not derived from barretenberg's implementations of them, and the lookup tables which they use, which set
+//! a minimum size for the circuit, are not modelled at all. Estimates for circuits which contain these opcodes are
+//! marked as not being [within tolerance](CircuitSizeEstimate::within_tolerance).
+
+use std::collections::HashMap;
+
+use acvm::acir::circuit::{
+    opcodes::{BlackBoxFuncCall, BlockId},
+    Circuit, Opcode,
+};
+use acvm::acir::native_types::Expression;
+
+/// The number of wires in each UltraPlonk gate.
+const WIRES: u64 = 4;
+
+/// The width of the arithmetic gates which ACIR expressions are laid out on.
+const EXPRESSION_WIDTH: usize = 3;
+
+/// The number of gates which barretenberg reserves at the end of every circuit.
+const RESERVED_GATES: u64 = 4;
+
+/// The number of gates which barretenberg adds to every circuit, to fix the value of its zero constant.
+const CONSTANT_GATES: u64 = 1;
+
+/// The number of bits in each limb of a range constraint which is too wide to check against a single list.
+const RANGE_LIMB_BITS: u32 = 14;
+
+/// The distance between consecutive values which barretenberg adds to each list of values checked by range constraints.
+const RANGE_STEP: u64 = 3;
+
+/// The maximum amount by which an estimate for a circuit of arithmetic and range constraints may differ
+/// from the size which barretenberg reports: 5% of that size or 8 gates, whichever is larger.
+///
+/// This is expressed as a relative and an absolute tolerance, in that order.
+pub const ESTIMATE_TOLERANCE: (f64, u32) = (0.05, 8);
+
+/// An estimate of the number of gates in a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitSizeEstimate {
+    pub gates: u32,
+    /// Whether every opcode in the circuit is modelled closely enough for `gates` to be within
+    /// [`ESTIMATE_TOLERANCE`] of barretenberg's count.
+    ///
+    /// This is false for circuits containing memory opcodes or black box functions other than `RANGE`,
+    /// whose estimates are only a rough indication of their size.
+    pub within_tolerance: bool,
+}
+
+/// Estimates the number of gates in `circuit`, as `get_exact_circuit_size` would report it,
+/// without running barretenberg.
+pub fn estimate_circuit_size(circuit: &Circuit) -> CircuitSizeEstimate {
+    let mut estimate = Estimate::default();
+    for opcode in &circuit.opcodes {
+        estimate.add(opcode);
+    }
+    let public_inputs = circuit.public_inputs().0.len() as u64;
+    CircuitSizeEstimate {
+        gates: u32::try_from(estimate.total() + public_inputs).unwrap_or(u32::MAX),
+        within_tolerance: !estimate.approximate,
+    }
+}
+
+/// Estimates the number of gates which each of `opcodes` adds to the circuit formed by the opcodes before it.
+pub(crate) fn opcode_estimates<'a>(opcodes: impl IntoIterator<Item = &'a Opcode>) -> Vec<u64> {
+    let mut estimate = Estimate::default();
+    let mut total = estimate.total();
+    opcodes
+        .into_iter()
+        .map(|opcode| {
+            estimate.add(opcode);
+            let previous_total = std::mem::replace(&mut total, estimate.total());
+            total.saturating_sub(previous_total)
+        })
+        .collect()
+}
+
+/// A block of memory, which barretenberg lays out as a read-only (ROM) array unless it is ever written to.
+#[derive(Debug, Default)]
+struct MemoryBlock {
+    size: u64,
+    reads: u64,
+    writes: u64,
+}
+
+/// The gates accumulated while laying out a circuit.
+#[derive(Default)]
+struct Estimate {
+    /// Gates which each belong to a single opcode.
+    gates: u64,
+    /// The number of values checked against each list of values, keyed by the largest value in the list.
+    range_lists: HashMap<u64, u64>,
+    memory_blocks: HashMap<BlockId, MemoryBlock>,
+    /// Whether any opcode has been added which is not modelled closely enough to be within [`ESTIMATE_TOLERANCE`].
+    approximate: bool,
+}
+
+impl Estimate {
+    fn add(&mut self, opcode: &Opcode) {
+        match opcode {
+            Opcode::Arithmetic(expression) => self.gates += expression_gates(expression),
+            // Directives and Brillig only compute witnesses, so do not constrain anything.
+            Opcode::Directive(_) | Opcode::Brillig(_) => {}
+            Opcode::MemoryInit { block_id, init } => {
+                self.approximate = true;
+                self.memory_blocks.entry(*block_id).or_default().size += init.len() as u64;
+            }
+            Opcode::MemoryOp { block_id, op } => {
+                self.approximate = true;
+                let block = self.memory_blocks.entry(*block_id).or_default();
+                if op.operation.is_zero() {
+                    block.reads += 1;
+                } else {
+                    block.writes += 1;
+                }
+            }
+            Opcode::BlackBoxFuncCall(call) => self.add_black_box(call),
+        }
+    }
+
+    fn add_black_box(&mut self, call: &BlackBoxFuncCall) {
+        let input_bytes = call.get_inputs_vec().len() as u64;
+        let gates = match call {
+            BlackBoxFuncCall::RANGE { input } => {
+                self.add_range_constraint(input.num_bits);
+                return;
+            }
+            BlackBoxFuncCall::AND { lhs, .. } | BlackBoxFuncCall::XOR { lhs, .. } => {
+                // Each 6-bit slice of the operands is looked up in a table, with the results accumulated alongside.
+                div_ceil(u64::from(lhs.num_bits), 6) + 1
+            }
+            BlackBoxFuncCall::SHA256 { .. } => 4_000 * div_ceil(input_bytes + 9, 64),
+            BlackBoxFuncCall::Blake2s { .. } | BlackBoxFuncCall::HashToField128Security { .. } => {
+                2_900 * div_ceil(input_bytes.max(1), 64)
+            }
+            BlackBoxFuncCall::Keccak256 { .. }
+            | BlackBoxFuncCall::Keccak256VariableLength { .. } => {
+                40_000 * div_ceil(input_bytes + 1, 136)
+            }
+            BlackBoxFuncCall::Pedersen { inputs, .. } => 250 * inputs.len().max(1) as u64,
+            BlackBoxFuncCall::SchnorrVerify { message, .. } => {
+                12_000 + 2_900 * div_ceil(message.len() as u64 + 64, 64)
+            }
+            BlackBoxFuncCall::EcdsaSecp256k1 { .. } => 36_000,
+            BlackBoxFuncCall::EcdsaSecp256r1 { .. } => 40_000,
+            BlackBoxFuncCall::FixedBaseScalarMul { .. } => 2_800,
+            BlackBoxFuncCall::RecursiveAggregation { .. } => 600_000,
+        };
+        // These costs are rough guesses at the size of each function, which do not follow how barretenberg
+        // lays it out, so only indicate whether a circuit is dominated by such calls.
+        self.approximate = true;
+        self.gates += gates;
+    }
+
+    /// Constrains a value to `num_bits`, which barretenberg does by checking it against a list of every value
+    /// in range. Values wider than a single list are split into limbs, which are each checked separately.
+    fn add_range_constraint(&mut self, num_bits: u32) {
+        if num_bits <= RANGE_LIMB_BITS {
+            self.add_to_range_list(num_bits);
+            return;
+        }
+
+        let full_limbs = num_bits / RANGE_LIMB_BITS;
+        let remainder_bits = num_bits % RANGE_LIMB_BITS;
+        for _ in 0..full_limbs {
+            self.add_to_range_list(RANGE_LIMB_BITS);
+        }
+        if remainder_bits > 0 {
+            self.add_to_range_list(remainder_bits);
+        }
+
+        // The limbs are recombined three at a time, with one gate for each.
+        let limbs = u64::from(full_limbs + u32::from(remainder_bits > 0));
+        self.gates += div_ceil(limbs, 3);
+    }
+
+    fn add_to_range_list(&mut self, num_bits: u32) {
+        let largest_value = (1u64 << num_bits) - 1;
+        *self.range_lists.entry(largest_value).or_default() += 1;
+    }
+
+    fn total(&self) -> u64 {
+        let range_gates: u64 = self
+            .range_lists
+            .iter()
+            .map(|(&largest_value, &checked_values)| {
+                range_list_gates(largest_value, checked_values)
+            })
+            .sum();
+        let memory_gates: u64 = self.memory_blocks.values().map(memory_block_gates).sum();
+        CONSTANT_GATES + self.gates + range_gates + memory_gates + RESERVED_GATES
+    }
+}
+
+/// Returns the number of gates used to lay out `expression`.
+fn expression_gates(expression: &Expression) -> u64 {
+    if expression.fits_in_one_identity(EXPRESSION_WIDTH) {
+        return 1;
+    }
+    // Wider expressions are split across several gates, each of which carries the sum of the previous gate forward.
+    let extra_terms = expression.mul_terms.len().saturating_sub(1)
+        + expression
+            .linear_combinations
+            .len()
+            .saturating_sub(EXPRESSION_WIDTH);
+    1 + div_ceil(extra_terms.max(1) as u64, EXPRESSION_WIDTH as u64 - 1)
+}
+
+/// Returns the number of gates used to check `checked_values` values against the list of values up to `largest_value`.
+fn range_list_gates(largest_value: u64, checked_values: u64) -> u64 {
+    // The list holds every multiple of the step up to the largest value, along with the largest value itself,
+    // which are laid out on gates of their own so that they are part of the witness.
+    let list_values = largest_value / RANGE_STEP + 1 + u64::from(largest_value % RANGE_STEP != 0);
+    let list_gates = div_ceil(list_values, WIRES);
+
+    // The list and the values checked against it are then sorted together, which is checked across consecutive
+    // gates. The sorted values are padded to fill the last gate, and a final gate checks the largest value.
+    let sorted_values = list_values + checked_values;
+    let mut padding = (WIRES - sorted_values % WIRES) % WIRES;
+    if sorted_values == WIRES {
+        padding += WIRES;
+    }
+    list_gates + (sorted_values + padding) / WIRES + 1
+}
+
+/// Returns the number of gates used to lay out `block`, along with every access to it.
+fn memory_block_gates(block: &MemoryBlock) -> u64 {
+    // Each access to an array is recorded on a gate, then again once the records are sorted.
+    // A final gate completes the sorted records.
+    let records = block.size + block.reads + block.writes;
+    let gates = 2 * records + 1;
+    if block.writes == 0 {
+        return gates;
+    }
+
+    // Writable (RAM) arrays also timestamp each access, with the difference between consecutive timestamps
+    // being range constrained to the number of accesses.
+    let timestamp_range = range_list_gates(records, records.saturating_sub(1));
+    gates + records + timestamp_range
+}
+
+/// Divides `numerator` by `denominator`, rounding up.
+fn div_ceil(numerator: u64, denominator: u64) -> u64 {
+    (numerator + denominator - 1) / denominator
+}
+
+/// Returns whether `estimate` is within [`ESTIMATE_TOLERANCE`] of the `actual` number of gates.
+#[cfg(test)]
+fn within_tolerance(estimate: u32, actual: u32) -> bool {
+    let (relative, absolute) = ESTIMATE_TOLERANCE;
+    let tolerance = (f64::from(actual) * relative).max(f64::from(absolute));
+    f64::from(estimate.abs_diff(actual)) <= tolerance
+}
+
+#[cfg(test)]
+fn read_1_mul() -> Circuit {
+    use base64::Engine;
+
+    let bytecode = std::fs::read_to_string("./src/1_mul.bytecode").unwrap();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(bytecode.trim())
+        .unwrap();
+    Circuit::read(&*bytes).unwrap()
+}
+
+/// Circuits of arithmetic and range constraints, whose estimates are compared against barretenberg.
+#[cfg(test)]
+fn sample_circuits() -> Vec<(&'static str, Circuit)> {
+    use acvm::acir::circuit::opcodes::FunctionInput;
+    use acvm::acir::native_types::Witness;
+    use acvm::FieldElement;
+
+    let range = |witness, num_bits| {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+            input: FunctionInput {
+                witness: Witness(witness),
+                num_bits,
+            },
+        })
+    };
+    let product = |lhs, rhs, output| {
+        let mut expression = Expression::default();
+        expression.push_multiplication_term(FieldElement::one(), Witness(lhs), Witness(rhs));
+        expression.push_addition_term(-FieldElement::one(), Witness(output));
+        Opcode::Arithmetic(expression)
+    };
+
+    vec![
+        ("1_mul", read_1_mul()),
+        (
+            "products",
+            Circuit {
+                current_witness_index: 20,
+                opcodes: (1..20)
+                    .map(|witness| product(witness, witness, witness + 1))
+                    .collect(),
+                ..Circuit::default()
+            },
+        ),
+        (
+            "narrow ranges",
+            Circuit {
+                current_witness_index: 8,
+                opcodes: (1..=8).map(|witness| range(witness, 8)).collect(),
+                ..Circuit::default()
+            },
+        ),
+        (
+            "wide ranges",
+            Circuit {
+                current_witness_index: 4,
+                opcodes: vec![range(1, 64), range(2, 64), range(3, 17), range(4, 1)],
+                ..Circuit::default()
+            },
+        ),
+    ]
+}
+
+#[test]
+fn estimate_for_1_mul_is_within_tolerance() {
+    // The number of gates which `bb gates` reports for `1_mul.bytecode`, as checked by `bb::gates::gate_command`.
+    let estimate = estimate_circuit_size(&read_1_mul());
+    assert!(estimate.within_tolerance, "1_mul is not fully modelled");
+    let estimate = estimate.gates;
+    assert!(
+        within_tolerance(estimate, 2775),
+        "estimated {estimate} gates for 1_mul, whereas barretenberg counts 2775"
+    );
+}
+
+#[test]
+fn estimates_match_gates_command() {
+    use tempfile::tempdir;
+
+    use crate::bb::{CliShimConfig, GatesCommand, InputFile};
+    use crate::proof_system::serialize_circuit;
+
+    let config = CliShimConfig::from_env();
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let temp_directory_path = temp_directory.path();
+    let path_to_crs = temp_directory_path.join("crs");
+
+    for (name, circuit) in sample_circuits() {
+        let bytecode_path = temp_directory_path.join(name).with_extension("bytecode");
+        std::fs::write(&bytecode_path, serialize_circuit(&circuit).unwrap()).unwrap();
+
+        let gate_count = GatesCommand {
+            path_to_crs: path_to_crs.to_str().unwrap().to_string(),
+            bytecode: InputFile::Path(bytecode_path.to_str().unwrap().to_string()),
+        }
+        .run(&config)
+        .unwrap();
+
+        let estimate = estimate_circuit_size(&circuit);
+        assert!(estimate.within_tolerance, "{name} is not fully modelled");
+        let estimate = estimate.gates;
+        assert!(
+            within_tolerance(estimate, gate_count),
+            "estimated {estimate} gates for {name}, whereas barretenberg counts {gate_count}"
+        );
+    }
+    drop(temp_directory);
+}
+
+#[test]
+fn range_constraints_share_lists() {
+    use acvm::acir::circuit::opcodes::FunctionInput;
+    use acvm::acir::native_types::Witness;
+
+    let range = |num_bits| {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+            input: FunctionInput {
+                witness: Witness(1),
+                num_bits,
+            },
+        })
+    };
+    let estimate = |opcodes| {
+        estimate_circuit_size(&Circuit {
+            current_witness_index: 1,
+            opcodes,
+            ..Circuit::default()
+        })
+        .gates
+    };
+
+    // The first constraint of a width pays for its list of values, whereas later ones are almost free.
+    let empty = estimate(vec![]);
+    let one = estimate(vec![range(8)]);
+    let two = estimate(vec![range(8), range(8)]);
+    assert_eq!(empty, CONSTANT_GATES as u32 + RESERVED_GATES as u32);
+    assert!(one - empty > 40);
+    assert!(two - one <= 1);
+
+    // Wide constraints are split into limbs, which share the lists of narrower constraints.
+    let wide = estimate(vec![range(32)]);
+    let wide_with_narrow = estimate(vec![range(32), range(14), range(4)]);
+    assert!(wide_with_narrow - wide <= 2);
+}
+
+#[test]
+fn unmodelled_opcodes_are_not_within_tolerance() {
+    use acvm::acir::circuit::opcodes::FunctionInput;
+    use acvm::acir::native_types::Witness;
+
+    let input = FunctionInput {
+        witness: Witness(1),
+        num_bits: 8,
+    };
+    let estimate = |opcodes| {
+        estimate_circuit_size(&Circuit {
+            current_witness_index: 2,
+            opcodes,
+            ..Circuit::default()
+        })
+    };
+
+    let range = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input });
+    assert!(estimate(vec![range.clone()]).within_tolerance);
+
+    let sha256 = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::SHA256 {
+        inputs: vec![input],
+        outputs: vec![Witness(2)],
+    });
+    assert!(!estimate(vec![range.clone(), sha256]).within_tolerance);
+
+    let memory_init = Opcode::MemoryInit {
+        block_id: BlockId(0),
+        init: vec![Witness(1)],
+    };
+    assert!(!estimate(vec![range, memory_init]).within_tolerance);
+}
@@ -0,0 +1,289 @@
+use acvm::acir::circuit::{opcodes::BlackBoxFuncCall, Circuit, Opcode};
+use acvm::acir::BlackBoxFunc;
+
+use crate::proof_system::{gates_command, serialize_circuit};
+use crate::{estimate, BackendError, Barretenberg, Error};
+
+/// The kind of an ACIR opcode, by which the gates in a [`GateBreakdown`] are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpcodeKind {
+    Arithmetic,
+    Directive,
+    Brillig,
+    MemoryInit,
+    MemoryOp,
+    /// A range constraint, which costs more gates the more bits that it constrains.
+    Range {
+        num_bits: u32,
+    },
+    /// A call to any black box function other than `RANGE`.
+    BlackBox(BlackBoxFunc),
+}
+
+impl OpcodeKind {
+    /// Returns the kind of `opcode`.
+    pub fn of(opcode: &Opcode) -> OpcodeKind {
+        match opcode {
+            Opcode::Arithmetic(_) => OpcodeKind::Arithmetic,
+            Opcode::Directive(_) => OpcodeKind::Directive,
+            Opcode::Brillig(_) => OpcodeKind::Brillig,
+            Opcode::MemoryInit { .. } => OpcodeKind::MemoryInit,
+            Opcode::MemoryOp { .. } => OpcodeKind::MemoryOp,
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input }) => OpcodeKind::Range {
+                num_bits: input.num_bits,
+            },
+            Opcode::BlackBoxFuncCall(call) => OpcodeKind::BlackBox(call.get_black_box_func()),
+        }
+    }
+}
+
+/// The gates added to a circuit by a single opcode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpcodeGates {
+    /// The index of the opcode within the circuit.
+    pub index: usize,
+    pub kind: OpcodeKind,
+    pub gates: u32,
+}
+
+/// The gates added to a circuit by every opcode of a single kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindGates {
+    pub kind: OpcodeKind,
+    /// The number of opcodes of this kind in the circuit.
+    pub opcodes: usize,
+    pub gates: u32,
+}
+
+/// The number of gates in a circuit, broken down by the opcodes which are responsible for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GateBreakdown {
+    /// The number of gates in the circuit with its opcodes grouped by kind, as barretenberg counted them.
+    ///
+    /// This is usually the size which `get_exact_circuit_size` reports, though the layout of some opcodes,
+    /// such as memory accesses, can depend on their order.
+    pub total: u32,
+    /// The gates which barretenberg adds to a circuit without any opcodes, such as those for its public inputs.
+    pub baseline: u32,
+    /// The gates added by each opcode, in the order in which the opcodes appear in the circuit.
+    pub opcodes: Vec<OpcodeGates>,
+    /// The gates added by each kind of opcode, from the most gates to the fewest.
+    pub kinds: Vec<KindGates>,
+}
+
+impl Barretenberg {
+    /// Counts the gates in `circuit`, attributing them to the opcodes which are responsible for them.
+    ///
+    /// Barretenberg only reports the number of gates in a whole circuit, so the opcodes are grouped by kind
+    /// and each kind is attributed the number of gates which it adds to the circuit formed by the kinds before it.
+    /// A kind which shares lookup tables with earlier kinds may therefore be attributed fewer gates than it would
+    /// be on its own. The gates of each kind are then split between its opcodes in proportion to their
+    /// [estimated](crate::estimate_circuit_size) sizes, or evenly if none of them are estimated to add any.
+    ///
+    /// The gates of every kind and the baseline sum to the total, unless adding a kind shrinks the circuit,
+    /// as padding occasionally does. Such a kind is attributed no gates, so the sum then exceeds the total.
+    ///
+    /// This runs barretenberg once for each kind of opcode in the circuit, and once more for the baseline.
+    pub fn gate_breakdown(&self, circuit: &Circuit) -> Result<GateBreakdown, BackendError> {
+        // Group the opcodes by kind, in the order in which each kind first appears.
+        let mut groups: Vec<(OpcodeKind, Vec<usize>)> = Vec::new();
+        for (index, opcode) in circuit.opcodes.iter().enumerate() {
+            let kind = OpcodeKind::of(opcode);
+            match groups
+                .iter_mut()
+                .find(|(group_kind, _)| *group_kind == kind)
+            {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((kind, vec![index])),
+            }
+        }
+        let estimates = estimate::opcode_estimates(
+            groups
+                .iter()
+                .flat_map(|(_, indices)| indices)
+                .map(|&index| &circuit.opcodes[index]),
+        );
+
+        // The partial circuits are counted without consulting the artifact cache, which would otherwise fill
+        // with their counts, and share the setup which would otherwise be repeated for each of them.
+        let temp_directory = self.temp_directory()?;
+        let path_to_crs = self.crs_directory(&[])?;
+        let streams_io = self.streams_io()?;
+        let count_gates = |circuit: &Circuit| -> Result<u32, Error> {
+            let command = gates_command(
+                serialize_circuit(circuit)?,
+                path_to_crs.clone(),
+                streams_io,
+                temp_directory.path(),
+            )?;
+            Ok(command.run(&self.config)?)
+        };
+
+        let mut prefix = Circuit {
+            opcodes: Vec::with_capacity(circuit.opcodes.len()),
+            ..circuit.clone()
+        };
+        let baseline = count_gates(&prefix)?;
+
+        let mut total = baseline;
+        let mut opcodes = Vec::with_capacity(circuit.opcodes.len());
+        let mut kinds = Vec::with_capacity(groups.len());
+        for (kind, indices) in groups {
+            let start = prefix.opcodes.len();
+            prefix
+                .opcodes
+                .extend(indices.iter().map(|&index| circuit.opcodes[index].clone()));
+            let gate_count = count_gates(&prefix)?;
+            let gates = gate_count.saturating_sub(total);
+            total = gate_count;
+
+            let shares = apportion(gates, &estimates[start..prefix.opcodes.len()]);
+            opcodes.extend(
+                indices
+                    .iter()
+                    .zip(shares)
+                    .map(|(&index, gates)| OpcodeGates { index, kind, gates }),
+            );
+            kinds.push(KindGates {
+                kind,
+                opcodes: indices.len(),
+                gates,
+            });
+        }
+        opcodes.sort_by_key(|opcode| opcode.index);
+        // The sort is stable, so kinds with as many gates as each other stay in the order in which they first appear.
+        kinds.sort_by_key(|kind| std::cmp::Reverse(kind.gates));
+
+        Ok(GateBreakdown {
+            total,
+            baseline,
+            opcodes,
+            kinds,
+        })
+    }
+}
+
+/// Splits `gates` between opcodes in proportion to their `estimates`, or evenly if every estimate is zero.
+///
+/// Any gates left over from rounding down go to the earliest opcodes which are estimated to add any.
+fn apportion(gates: u32, estimates: &[u64]) -> Vec<u32> {
+    let weights: Vec<u64> = if estimates.iter().all(|&estimate| estimate == 0) {
+        vec![1; estimates.len()]
+    } else {
+        estimates.to_vec()
+    };
+    let total_weight: u128 = weights.iter().map(|&weight| u128::from(weight)).sum();
+
+    let mut shares: Vec<u32> = weights
+        .iter()
+        .map(|&weight| (u128::from(gates) * u128::from(weight) / total_weight) as u32)
+        .collect();
+    let mut remainder = gates - shares.iter().sum::<u32>();
+    for (share, &weight) in shares.iter_mut().zip(&weights) {
+        if remainder == 0 {
+            break;
+        }
+        if weight > 0 {
+            *share += 1;
+            remainder -= 1;
+        }
+    }
+    shares
+}
+
+#[test]
+fn gates_are_attributed_to_opcodes() {
+    use acvm::acir::{
+        circuit::opcodes::FunctionInput,
+        native_types::{Expression, Witness},
+    };
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    let calls_path = temp_directory.path().join("calls");
+    // Successive calls count 5, 15, 45 and then 40 gates.
+    crate::bb::write_mock_binary(
+        &binary_path,
+        &format!(
+            r#"#!/bin/sh
+[ "$1" = --version ] && echo 0.4.6 && exit 0
+calls={calls}
+n=$(cat "$calls" 2>/dev/null || echo 0)
+echo $((n + 1)) > "$calls"
+set -- 5 15 45 40
+shift $n
+printf "\\$(printf %o $1)\000\000\000\000\000\000\000"
+"#,
+            calls = calls_path.display()
+        ),
+    );
+
+    let range = |num_bits| {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+            input: FunctionInput {
+                witness: Witness(1),
+                num_bits,
+            },
+        })
+    };
+    let circuit = Circuit {
+        current_witness_index: 1,
+        opcodes: vec![
+            Opcode::Arithmetic(Expression::default()),
+            range(8),
+            range(32),
+            range(8),
+        ],
+        ..Circuit::default()
+    };
+
+    let backend = Barretenberg::builder()
+        .binary_path(binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .build();
+    let breakdown = backend.gate_breakdown(&circuit).unwrap();
+
+    // Barretenberg is run once for the baseline and once for each of the three kinds.
+    assert_eq!(std::fs::read_to_string(&calls_path).unwrap().trim(), "4");
+    assert_eq!(breakdown.baseline, 5);
+    assert_eq!(breakdown.total, 40);
+    // The second 8-bit constraint shares the list of values checked by the first, so adds no gates of its own.
+    // Adding the 32-bit constraint shrank the circuit, so it is attributed none.
+    let opcodes: Vec<(usize, u32)> = breakdown
+        .opcodes
+        .iter()
+        .map(|opcode| (opcode.index, opcode.gates))
+        .collect();
+    assert_eq!(opcodes, [(0, 10), (1, 30), (2, 0), (3, 0)]);
+    assert_eq!(
+        breakdown.kinds,
+        [
+            KindGates {
+                kind: OpcodeKind::Range { num_bits: 8 },
+                opcodes: 2,
+                gates: 30,
+            },
+            KindGates {
+                kind: OpcodeKind::Arithmetic,
+                opcodes: 1,
+                gates: 10,
+            },
+            KindGates {
+                kind: OpcodeKind::Range { num_bits: 32 },
+                opcodes: 1,
+                gates: 0,
+            },
+        ]
+    );
+
+    drop(temp_directory);
+}
+
+#[test]
+fn gates_are_apportioned_by_estimate() {
+    assert_eq!(apportion(10, &[3, 1]), [8, 2]);
+    assert_eq!(apportion(10, &[0, 5, 5]), [0, 5, 5]);
+    assert_eq!(apportion(5, &[0, 0]), [3, 2]);
+    assert_eq!(apportion(0, &[4]), [0]);
+}
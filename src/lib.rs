@@ -13,19 +13,194 @@ compile_error!("feature \"native\" cannot be enabled for a \"wasm32\" target");
 #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 compile_error!("feature \"wasm\" cannot be enabled for a \"wasm32\" target");
 
+use std::path::{Path, PathBuf};
+
+use acvm::acir::{circuit::Circuit, native_types::WitnessMapError};
+use acvm::ProofSystemCompiler;
+use tempfile::TempDir;
+
+mod artifacts;
+#[cfg(feature = "async")]
+mod async_api;
 mod bb;
+mod builder;
+mod crs;
+mod estimate;
+mod gate_breakdown;
+mod preprocess;
 mod proof_system;
+mod recursion;
+mod report;
+mod secrets;
 mod smart_contract;
+mod trace;
+
+use artifacts::{ArtifactCache, CacheEntry};
+pub use bb::{
+    BinaryManifest, CachedBinary, CancellationHandle, Operation, ProgressEvent, ProgressPhase,
+};
+use bb::{CliShimConfig, CliShimError};
+pub use builder::BarretenbergBuilder;
+pub use crs::CrsStatus;
+use crs::CrsStore;
+pub use estimate::{estimate_circuit_size, CircuitSizeEstimate, ESTIMATE_TOLERANCE};
+pub use gate_breakdown::{GateBreakdown, KindGates, OpcodeGates, OpcodeKind};
+pub use report::{CommandUsage, PerformanceReport, PhaseTiming, ReportPhase};
 
 /// The number of bytes necessary to store a `FieldElement`.
 const FIELD_BYTES: usize = 32;
 
-#[derive(Debug, Default)]
-pub struct Barretenberg;
+#[derive(Debug, Clone)]
+pub struct Barretenberg {
+    config: CliShimConfig,
+    crs_dir: Option<PathBuf>,
+    temp_dir: Option<PathBuf>,
+    witness_dir: Option<PathBuf>,
+    regenerate_verification_key: bool,
+    artifact_cache: Option<ArtifactCache>,
+}
+
+impl Default for Barretenberg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Barretenberg {
+    /// Creates a backend which is configured through environment variables.
+    ///
+    /// See [`BarretenbergBuilder`] for configuring the backend directly.
     pub fn new() -> Barretenberg {
-        Barretenberg
+        BarretenbergBuilder::new().build()
+    }
+
+    /// Returns a builder for a backend whose settings fall back to environment variables when they are not set.
+    pub fn builder() -> BarretenbergBuilder {
+        BarretenbergBuilder::new()
+    }
+
+    /// Returns a copy of this backend whose barretenberg commands are killed once `cancellation` is cancelled.
+    ///
+    /// Commands which are cancelled fail with an error for which [`BackendError::is_cancelled`] returns `true`.
+    /// The copy shares this backend's caches and workers, so a handle can be created per request.
+    pub fn with_cancellation(&self, cancellation: CancellationHandle) -> Barretenberg {
+        let mut backend = self.clone();
+        backend.config.cancellation = Some(cancellation);
+        backend
+    }
+
+    /// Installs the barretenberg binary from local files rather than downloading it.
+    ///
+    /// `source` may be a release archive (`.tar.gz`), a directory containing an extracted release archive
+    /// or a prebuilt `bb` executable. The binary is installed to the location from which this backend
+    /// will run it, which is returned. Combined with offline mode, this allows the backend
+    /// to be used without any network access.
+    pub fn install_binary(&self, source: &Path) -> Result<PathBuf, BackendError> {
+        Ok(bb::install_binary(&self.config, source)?)
+    }
+
+    /// Returns the barretenberg binaries stored in the binary cache.
+    ///
+    /// Binaries are cached per barretenberg release under `~/.nargo/backends/acvm-backend-barretenberg`,
+    /// unless another cache directory is configured, alongside a manifest recording where each binary
+    /// was installed from.
+    pub fn cached_binaries(&self) -> Result<Vec<CachedBinary>, BackendError> {
+        Ok(bb::cached_binaries(&self.config)?)
+    }
+
+    /// Downloads the barretenberg binary for the release `version` into the binary cache, returning its path.
+    ///
+    /// The release archive must match `archive_sha256` if it is provided. Only the release targeted by this crate
+    /// has a pinned digest, and the digests configured for this backend only apply to that release,
+    /// so other releases must have their expected digest provided here.
+    pub fn install_version(
+        &self,
+        version: &str,
+        archive_sha256: Option<&str>,
+    ) -> Result<PathBuf, BackendError> {
+        Ok(bb::install_version(&self.config, version, archive_sha256)?)
+    }
+
+    /// Removes cached binaries for any releases other than those in `keep`, returning the removed releases.
+    ///
+    /// The release targeted by this crate is always kept.
+    pub fn prune_cached_binaries(&self, keep: &[&str]) -> Result<Vec<String>, BackendError> {
+        Ok(bb::prune_cached_binaries(&self.config, keep)?)
+    }
+
+    /// Creates a temporary directory in which to store the files passed to barretenberg.
+    fn temp_directory(&self) -> Result<TempDir, Error> {
+        match &self.temp_dir {
+            Some(temp_dir) => tempfile::tempdir_in(temp_dir),
+            None => tempfile::tempdir(),
+        }
+        .map_err(Error::TempDirectory)
+    }
+
+    /// Returns whether data should be streamed to barretenberg rather than written to temporary files.
+    fn streams_io(&self) -> Result<bool, Error> {
+        if !self.config.stream_io {
+            return Ok(false);
+        }
+        let probe_bytecode = proof_system::serialize_circuit(&Circuit::default())?;
+        Ok(bb::supports_streaming(
+            &self.config,
+            probe_bytecode.as_bytes(),
+        )?)
+    }
+
+    /// Creates a temporary directory in which to store the witnesses passed to barretenberg.
+    ///
+    /// This is created within the configured witness directory if there is one, so that these files
+    /// can be kept apart from other temporary files, such as on a filesystem which is never written to disk.
+    fn secret_directory(&self) -> Result<TempDir, Error> {
+        match &self.witness_dir {
+            Some(witness_dir) => tempfile::tempdir_in(witness_dir).map_err(Error::TempDirectory),
+            None => self.temp_directory(),
+        }
+    }
+
+    /// Returns the cached artifacts for the circuit with the serialized `bytecode`, unless caching is disabled.
+    ///
+    /// Artifacts are cached per barretenberg binary, so caching is also skipped if the binary cannot be identified.
+    fn cached_artifacts(&self, bytecode: &str, is_recursive: bool) -> Option<CacheEntry> {
+        let cache = self.artifact_cache.as_ref()?;
+        let binary_sha256 = bb::binary_digest(&self.config).ok()?;
+        Some(cache.entry(bytecode.as_bytes(), &binary_sha256, is_recursive))
+    }
+
+    /// Reports how many points of the common reference string are stored compared to the number required by `circuit`.
+    pub fn crs_status(&self, circuit: &Circuit) -> Result<CrsStatus, BackendError> {
+        let num_gates = self.get_exact_circuit_size(circuit)?;
+        Ok(self.crs_store()?.status(crs::points_required(num_gates)))
+    }
+
+    /// Returns the store in which barretenberg keeps the common reference string.
+    ///
+    /// This defaults to a directory within the user's home directory unless another CRS directory is configured.
+    fn crs_store(&self) -> Result<CrsStore, Error> {
+        match &self.crs_dir {
+            Some(crs_dir) => Ok(CrsStore::new(crs_dir.clone())),
+            None => dirs::home_dir()
+                .map(|home| CrsStore::new(home.join(crs::DEFAULT_CRS_FOLDER)))
+                .ok_or(Error::CrsDirectory),
+        }
+    }
+
+    /// Returns the directory which barretenberg should read the common reference string from.
+    ///
+    /// If the caller supplied a `common_reference_string` then this is written into the store first.
+    fn crs_directory(&self, common_reference_string: &[u8]) -> Result<String, Error> {
+        let store = self.crs_store()?;
+        if common_reference_string.is_empty() {
+            std::fs::create_dir_all(store.directory()).map_err(|source| Error::WriteFile {
+                path: store.directory().to_path_buf(),
+                source,
+            })?;
+        } else {
+            store.materialize(common_reference_string)?;
+        }
+        proof_system::path_to_string(store.directory())
     }
 }
 
@@ -35,6 +210,81 @@ impl acvm::Backend for Barretenberg {}
 #[error(transparent)]
 pub struct BackendError(#[from] Error);
 
+impl BackendError {
+    /// Returns whether a barretenberg command was killed because it did not complete within its timeout.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.0, Error::CliShim(CliShimError::Timeout { .. }))
+    }
+
+    /// Returns whether a barretenberg command was killed because it was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.0, Error::CliShim(CliShimError::Cancelled { .. }))
+    }
+
+    /// Returns whether a barretenberg command was killed for exceeding its memory or CPU time limit.
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self.0,
+            Error::CliShim(
+                CliShimError::MemoryLimitExceeded { .. }
+                    | CliShimError::CpuTimeLimitExceeded { .. }
+            )
+        )
+    }
+}
+
+impl From<CliShimError> for BackendError {
+    fn from(error: CliShimError) -> BackendError {
+        BackendError(Error::from(error))
+    }
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, thiserror::Error)]
-enum Error {}
+enum Error {
+    #[error(transparent)]
+    CliShim(#[from] CliShimError),
+
+    #[error("Could not create a temporary directory: {0}")]
+    TempDirectory(std::io::Error),
+    #[error("Could not write to {}: {source}", path.display())]
+    WriteFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not read from {}: {source}", path.display())]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Path {} is not valid UTF-8", .0.display())]
+    NonUtf8Path(PathBuf),
+
+    #[error("Could not determine a directory for the common reference string, one must be configured explicitly")]
+    CrsDirectory,
+    #[error("Common reference string of {0} bytes is not a sequence of G1 points followed by a G2 point")]
+    InvalidCrs(usize),
+
+    #[error(
+        "Proof of {0} bytes is not an UltraPlonk proof, which is {} bytes long",
+        recursion::PROOF_BYTES
+    )]
+    MalformedProof(usize),
+    #[error("Verification key is malformed: {0}")]
+    MalformedVerificationKey(String),
+    #[error("No verification key was provided and regenerating it from the circuit is disabled")]
+    MissingVerificationKey,
+    #[error("Verification key is for a circuit with {found} public inputs but the circuit has {expected}")]
+    VerificationKeyMismatch { expected: usize, found: usize },
+    #[error("Verification key is for a circuit padded to {found} gates but the circuit is padded to {expected}")]
+    VerificationKeySizeMismatch { expected: u32, found: u32 },
+
+    #[error("Could not serialize circuit: {0}")]
+    CircuitSerialization(std::io::Error),
+    #[error("Could not serialize witness map: {0}")]
+    WitnessSerialization(#[from] WitnessMapError),
+    #[error("Smart contract generated by barretenberg is not valid UTF-8")]
+    NonUtf8Contract(#[from] std::string::FromUtf8Error),
+}
@@ -0,0 +1,316 @@
+use std::path::{Path, PathBuf};
+
+use acvm::acir::circuit::Circuit;
+
+use crate::{
+    artifacts::Artifact,
+    bb::WriteVkCommand,
+    crs,
+    proof_system::{
+        cached_gate_count, path_to_string, read_bytes_from_file, serialize_circuit, write_to_file,
+    },
+    recursion,
+    report::ReportPhase,
+    trace, BackendError, Barretenberg, Error,
+};
+
+impl Barretenberg {
+    /// Constructs the proving and verification keys for `circuit`, returning them in that order.
+    ///
+    /// The verification key may be stored as a build artifact and passed back into `verify_with_vk`
+    /// and `eth_contract_from_vk` with the same `is_recursive`, so that it does not need to be constructed again.
+    /// Barretenberg cannot load a proving key, so the proving key is empty until it can, and `prove_with_pk`
+    /// constructs the key itself.
+    pub fn preprocess(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        is_recursive: bool,
+    ) -> Result<(Vec<u8>, Vec<u8>), BackendError> {
+        let _circuit_span = trace::enter_circuit(circuit);
+        let serialized_circuit =
+            self.timed(ReportPhase::Serialization, || serialize_circuit(circuit))?;
+        let cached_artifacts = self.cached_artifacts(&serialized_circuit, is_recursive);
+        if let Some(verification_key) = cached_artifacts
+            .as_ref()
+            .and_then(|artifacts| artifacts.get(Artifact::VerificationKey))
+        {
+            return Ok((Vec::new(), verification_key));
+        }
+
+        let temp_directory = self.temp_directory()?;
+        let temp_directory = temp_directory.path();
+        let path_to_crs = self.timed(ReportPhase::FileWrites, || {
+            self.crs_directory(common_reference_string)
+        })?;
+
+        // Create a temporary file for the circuit
+        let circuit_path = temp_directory.join("circuit").with_extension("bytecode");
+        self.timed(ReportPhase::FileWrites, || {
+            write_to_file(serialized_circuit.as_bytes(), &circuit_path)
+        })?;
+
+        let vk_path = temp_directory.join("vk");
+        let write_vk = WriteVkCommand {
+            verbose: self.config.verbose.unwrap_or(false),
+            path_to_crs,
+            is_recursive,
+            path_to_bytecode: path_to_string(&circuit_path)?,
+            path_to_vk_output: path_to_string(&vk_path)?,
+        };
+        self.timed(ReportPhase::Subprocess, || write_vk.run(&self.config))?;
+
+        let verification_key =
+            self.timed(ReportPhase::FileReads, || read_bytes_from_file(&vk_path))?;
+        if let Some(artifacts) = cached_artifacts {
+            artifacts.put(Artifact::VerificationKey, &verification_key);
+        }
+        Ok((Vec::new(), verification_key))
+    }
+
+    /// Writes the verification key for `circuit` into `temp_directory`, returning its path.
+    ///
+    /// The caller-supplied `verification_key` is used if one is provided, after checking that it belongs
+    /// to `circuit`. Its circuit size is only checked if the circuit's gate count is already cached,
+    /// as counting the gates would cost as much as building the circuit. Otherwise the key is regenerated from `circuit`, if `regenerate` allows it.
+    pub(crate) fn write_verification_key(
+        &self,
+        temp_directory: &Path,
+        path_to_crs: &str,
+        circuit: &Circuit,
+        verification_key: &[u8],
+        is_recursive: bool,
+        regenerate: bool,
+    ) -> Result<PathBuf, Error> {
+        let vk_path = temp_directory.join("vk");
+        if verification_key.is_empty() {
+            if !regenerate {
+                return Err(Error::MissingVerificationKey);
+            }
+
+            let serialized_circuit = serialize_circuit(circuit)?;
+            let cached_artifacts = self.cached_artifacts(&serialized_circuit, is_recursive);
+            if let Some(verification_key) = cached_artifacts
+                .as_ref()
+                .and_then(|artifacts| artifacts.get(Artifact::VerificationKey))
+            {
+                write_to_file(&verification_key, &vk_path)?;
+                return Ok(vk_path);
+            }
+
+            // Create a temporary file for the circuit
+            let circuit_path = temp_directory.join("circuit").with_extension("bytecode");
+            write_to_file(serialized_circuit.as_bytes(), &circuit_path)?;
+
+            // Create the verification key and write it to the specified path
+            WriteVkCommand {
+                verbose: self.config.verbose.unwrap_or(false),
+                path_to_crs: path_to_crs.to_string(),
+                is_recursive,
+                path_to_bytecode: path_to_string(&circuit_path)?,
+                path_to_vk_output: path_to_string(&vk_path)?,
+            }
+            .run(&self.config)?;
+
+            if let Some(artifacts) = cached_artifacts {
+                artifacts.put(Artifact::VerificationKey, &read_bytes_from_file(&vk_path)?);
+            }
+        } else {
+            // Reject keys which clearly belong to a different circuit, rather than reporting an invalid proof.
+            let vk_info = recursion::check_verification_key(verification_key)?;
+            let num_public_inputs = circuit.public_inputs().0.len();
+            if vk_info.num_public_inputs as usize != num_public_inputs {
+                return Err(Error::VerificationKeyMismatch {
+                    expected: num_public_inputs,
+                    found: vk_info.num_public_inputs as usize,
+                });
+            }
+            let cached_artifacts = self.cached_artifacts(&serialize_circuit(circuit)?, false);
+            if let Some(gate_count) = cached_gate_count(cached_artifacts.as_ref()) {
+                let circuit_size = crs::padded_size(gate_count);
+                if vk_info.circuit_size != circuit_size {
+                    return Err(Error::VerificationKeySizeMismatch {
+                        expected: circuit_size,
+                        found: vk_info.circuit_size,
+                    });
+                }
+            }
+
+            write_to_file(verification_key, &vk_path)?;
+        }
+
+        Ok(vk_path)
+    }
+}
+
+#[test]
+fn preprocessed_keys_are_accepted() {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use acvm::{
+        acir::{
+            circuit::{Opcode, PublicInputs},
+            native_types::{Expression, Witness, WitnessMap},
+        },
+        FieldElement, ProofSystemCompiler,
+    };
+
+    let expression = &(Witness(1) + Witness(2)) - &Expression::from(Witness(3));
+    let circuit = Circuit {
+        current_witness_index: 4,
+        opcodes: vec![Opcode::Arithmetic(expression)],
+        private_parameters: BTreeSet::from([Witness(1), Witness(2)]),
+        public_parameters: PublicInputs(BTreeSet::from([Witness(3)])),
+        return_values: PublicInputs::default(),
+    };
+    let witness_values = WitnessMap::from(BTreeMap::from([
+        (Witness(1), FieldElement::from(1_u128)),
+        (Witness(2), FieldElement::from(2_u128)),
+        (Witness(3), FieldElement::from(3_u128)),
+    ]));
+    let public_inputs =
+        WitnessMap::from(BTreeMap::from([(Witness(3), FieldElement::from(3_u128))]));
+
+    let bb = Barretenberg::new();
+    let (proving_key, verification_key) = bb.preprocess(&[], &circuit, false).unwrap();
+    assert!(proving_key.is_empty());
+    assert!(recursion::check_verification_key(&verification_key).is_ok());
+
+    let proof = bb
+        .prove_with_pk(&[], &circuit, witness_values, &proving_key, false)
+        .unwrap();
+    assert!(bb
+        .verify_with_vk(
+            &[],
+            &proof,
+            public_inputs,
+            &circuit,
+            &verification_key,
+            false
+        )
+        .unwrap());
+}
+
+#[test]
+fn recursive_verification_keys_can_be_preprocessed() {
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    // The verification key records the arguments with which it was written.
+    crate::bb::write_mock_binary(
+        &binary_path,
+        r#"#!/bin/sh
+case "$1" in
+    --version) echo 0.4.6 ;;
+    write_vk)
+        arguments="$*"
+        while [ "$1" != -o ]; do shift; done
+        printf '%s' "$arguments" > "$2"
+        ;;
+    *) exit 1 ;;
+esac
+"#,
+    );
+    let bb = Barretenberg::builder()
+        .binary_path(binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .artifact_cache(false)
+        .build();
+
+    for is_recursive in [false, true] {
+        let (proving_key, verification_key) = bb
+            .preprocess(&[], &Circuit::default(), is_recursive)
+            .unwrap();
+        assert!(proving_key.is_empty());
+        let arguments = String::from_utf8(verification_key).unwrap();
+        assert_eq!(arguments.ends_with(" -r"), is_recursive, "{arguments}");
+    }
+
+    drop(temp_directory);
+}
+
+#[test]
+fn verification_key_must_match_circuit_size() {
+    use std::collections::BTreeSet;
+
+    use acvm::{
+        acir::{
+            circuit::{Opcode, PublicInputs},
+            native_types::{Expression, Witness, WitnessMap},
+        },
+        ProofSystemCompiler,
+    };
+
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    let counted_path = temp_directory.path().join("counted");
+    // The circuit has 10 gates, so is padded to 16. Every proof is valid.
+    crate::bb::write_mock_binary(
+        &binary_path,
+        &format!(
+            r#"#!/bin/sh
+case "$1" in
+    --version) echo 0.4.6 ;;
+    gates) touch {counted} && printf '\012\000\000\000\000\000\000\000' ;;
+    verify) exit 0 ;;
+    *) exit 1 ;;
+esac
+"#,
+            counted = counted_path.display()
+        ),
+    );
+    let builder = Barretenberg::builder()
+        .binary_path(binary_path)
+        .crs_dir(temp_directory.path().join("crs"));
+
+    let expression = &(Witness(1) + Witness(2)) - &Expression::from(Witness(3));
+    let circuit = Circuit {
+        current_witness_index: 4,
+        opcodes: vec![Opcode::Arithmetic(expression)],
+        private_parameters: BTreeSet::from([Witness(1), Witness(2)]),
+        public_parameters: PublicInputs(BTreeSet::from([Witness(3)])),
+        return_values: PublicInputs::default(),
+    };
+
+    // A key for a different circuit of 4096 gates, which also has a single public input.
+    let mut verification_key = Vec::new();
+    verification_key.extend(2_u32.to_be_bytes());
+    verification_key.extend(4096_u32.to_be_bytes());
+    verification_key.extend(1_u32.to_be_bytes());
+    verification_key.extend(1_u32.to_be_bytes());
+    verification_key.extend(3_u32.to_be_bytes());
+    verification_key.extend(b"Q_M");
+    verification_key.extend([0; 64]);
+    verification_key.push(0);
+    verification_key.extend(0_u32.to_be_bytes());
+
+    let verify = |bb: &Barretenberg| {
+        bb.verify_with_vk(
+            &[],
+            &[],
+            WitnessMap::new(),
+            &circuit,
+            &verification_key,
+            false,
+        )
+    };
+
+    // Without a cached gate count, the circuit is not built just to check the size of the key.
+    let bb = builder.clone().artifact_cache(false).build();
+    assert!(verify(&bb).unwrap());
+    assert!(!counted_path.exists());
+
+    let bb = builder
+        .artifact_cache_dir(temp_directory.path().join("artifacts"))
+        .build();
+    assert_eq!(bb.get_exact_circuit_size(&circuit).unwrap(), 10);
+    let result = verify(&bb);
+    assert!(matches!(
+        result,
+        Err(BackendError(Error::VerificationKeySizeMismatch {
+            expected: 16,
+            found: 4096
+        }))
+    ));
+
+    drop(temp_directory);
+}
@@ -1,15 +1,23 @@
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use acvm::acir::circuit::Opcode;
 use acvm::acir::{circuit::Circuit, native_types::WitnessMap, BlackBoxFunc};
 use acvm::FieldElement;
 use acvm::{Language, ProofSystemCompiler};
-use tempfile::tempdir;
-
-use crate::bb::{GatesCommand, ProveCommand, VerifyCommand, WriteVkCommand};
-use crate::{BackendError, Barretenberg, FIELD_BYTES};
+use tempfile::TempDir;
+use zeroize::Zeroizing;
+
+use crate::artifacts::{Artifact, CacheEntry};
+use crate::bb::{
+    CliShimError, GatesCommand, InputFile, OutputFile, ProveCommand, VerifyCommand,
+    VkAsFieldsCommand,
+};
+use crate::report::ReportPhase;
+use crate::secrets::SecretFile;
+use crate::trace;
+use crate::{recursion, BackendError, Barretenberg, Error, FIELD_BYTES};
 
 impl ProofSystemCompiler for Barretenberg {
     type Error = BackendError;
@@ -19,23 +27,8 @@ impl ProofSystemCompiler for Barretenberg {
     }
 
     fn get_exact_circuit_size(&self, circuit: &Circuit) -> Result<u32, Self::Error> {
-        let temp_directory = tempdir().expect("could not create a temporary directory");
-        let temp_directory = temp_directory.path();
-        let temp_dir_path_str = temp_directory.to_str().unwrap();
-
-        // Create a temporary file for the circuit
-        //
-        let circuit_path = temp_directory.join("circuit").with_extension("bytecode");
-        let serialized_circuit = serialize_circuit(circuit);
-        write_to_file(serialized_circuit.as_bytes(), &circuit_path);
-
-        let number_of_gates_needed = GatesCommand {
-            path_to_crs: temp_dir_path_str.to_string(),
-            path_to_bytecode: circuit_path.as_os_str().to_str().unwrap().to_string(),
-        }
-        .run();
-
-        Ok(number_of_gates_needed)
+        let _circuit_span = trace::enter_circuit(circuit);
+        Ok(self.gate_count(serialize_circuit(circuit)?)?)
     }
 
     fn supports_opcode(&self, opcode: &Opcode) -> bool {
@@ -65,45 +58,22 @@ impl ProofSystemCompiler for Barretenberg {
 
     fn prove_with_pk(
         &self,
-        _common_reference_string: &[u8],
+        common_reference_string: &[u8],
         circuit: &Circuit,
         witness_values: WitnessMap,
-        _proving_key: &[u8],
+        proving_key: &[u8],
         is_recursive: bool,
     ) -> Result<Vec<u8>, Self::Error> {
-        let temp_directory = tempdir().expect("could not create a temporary directory");
-        let temp_directory = temp_directory.path();
-        let temp_dir_path_str = temp_directory.to_str().unwrap();
-
-        // Create a temporary file for the witness
-        let serialized_witnesses: Vec<u8> = witness_values
-            .try_into()
-            .expect("could not serialize witness map");
-        let witness_path = temp_directory.join("witness").with_extension("tr");
-        write_to_file(&serialized_witnesses, &witness_path);
-
-        // Create a temporary file for the circuit
-        //
-        let circuit_path = temp_directory.join("circuit").with_extension("bytecode");
-        let serialized_circuit = serialize_circuit(circuit);
-        write_to_file(serialized_circuit.as_bytes(), &circuit_path);
-
-        let proof_path = temp_directory.join("proof").with_extension("proof");
-
-        // Create proof and store it in the specified path
-        ProveCommand {
-            verbose: true,
-            path_to_crs: temp_dir_path_str.to_string(),
+        let _circuit_span = trace::enter_circuit(circuit);
+        let (command, _temp_files) = self.prepare_proof(
+            common_reference_string,
+            circuit,
+            witness_values,
+            proving_key,
             is_recursive,
-            path_to_bytecode: circuit_path.as_os_str().to_str().unwrap().to_string(),
-            path_to_witness: witness_path.as_os_str().to_str().unwrap().to_string(),
-            path_to_proof: proof_path.as_os_str().to_str().unwrap().to_string(),
-        }
-        .run()
-        .expect("prove command failed");
-
+        )?;
         let proof_with_public_inputs =
-            read_bytes_from_file(proof_path.as_os_str().to_str().unwrap()).unwrap();
+            self.timed(ReportPhase::Subprocess, || command.run(&self.config))?;
 
         // Barretenberg return the proof prepended with the public inputs.
         //
@@ -112,23 +82,211 @@ impl ProofSystemCompiler for Barretenberg {
         //
         // TODO: As noted in the verification procedure, this is an abstraction leak
         // TODO: and will need modifications to barretenberg
-        let proof =
-            remove_public_inputs(circuit.public_inputs().0.len(), &proof_with_public_inputs);
+        let proof = self.timed(ReportPhase::PostProcessing, || {
+            remove_public_inputs(circuit.public_inputs().0.len(), &proof_with_public_inputs)
+        })?;
         Ok(proof)
     }
 
     fn verify_with_vk(
         &self,
-        _common_reference_string: &[u8],
+        common_reference_string: &[u8],
         proof: &[u8],
         public_inputs: WitnessMap,
         circuit: &Circuit,
-        _verification_key: &[u8],
+        verification_key: &[u8],
         is_recursive: bool,
     ) -> Result<bool, Self::Error> {
-        let temp_directory = tempdir().expect("could not create a temporary directory");
+        let _circuit_span = trace::enter_circuit(circuit);
+        let (command, _temp_files) = self.prepare_verification(
+            common_reference_string,
+            proof,
+            public_inputs,
+            circuit,
+            verification_key,
+            is_recursive,
+        )?;
+
+        // Verify the proof
+        Ok(command.run(&self.config)?)
+    }
+
+    fn proof_as_fields(
+        &self,
+        proof: &[u8],
+        public_inputs: WitnessMap,
+    ) -> Result<Vec<FieldElement>, Self::Error> {
+        // As when verifying, the public inputs are ordered by their witness index.
+        let flattened_public_inputs = public_inputs.into_iter().map(|(_, el)| el);
+        Ok(recursion::proof_as_fields(proof, flattened_public_inputs)?)
+    }
+
+    fn vk_as_fields(
+        &self,
+        common_reference_string: &[u8],
+        verification_key: &[u8],
+    ) -> Result<(Vec<FieldElement>, FieldElement), Self::Error> {
+        recursion::check_verification_key(verification_key)?;
+
+        let temp_directory = self.temp_directory()?;
         let temp_directory = temp_directory.path();
-        let temp_dir_path = temp_directory.to_str().unwrap();
+        let path_to_crs = self.crs_directory(common_reference_string)?;
+
+        // Create a temporary file for the verification key
+        let vk_path = temp_directory.join("vk");
+        write_to_file(verification_key, &vk_path)?;
+
+        let vk_as_fields_path = temp_directory.join("vk_as_fields");
+        Ok(VkAsFieldsCommand {
+            verbose: self.config.verbose.unwrap_or(false),
+            path_to_crs,
+            path_to_vk: path_to_string(&vk_path)?,
+            path_to_vk_as_fields: path_to_string(&vk_as_fields_path)?,
+        }
+        .run(&self.config)?)
+    }
+}
+
+/// The temporary files passed to barretenberg for a command, which are removed when this is dropped.
+///
+/// This must be kept alive until the command has run.
+pub(crate) struct TempFiles {
+    // Fields are dropped in order, so secret files are wiped before the directories holding them are removed.
+    _secret_files: Vec<SecretFile>,
+    _secret_directory: Option<TempDir>,
+    _temp_directory: TempDir,
+}
+
+impl TempFiles {
+    /// Holds a temporary directory which does not contain any secrets.
+    pub(crate) fn new(temp_directory: TempDir) -> TempFiles {
+        TempFiles {
+            _secret_files: Vec::new(),
+            _secret_directory: None,
+            _temp_directory: temp_directory,
+        }
+    }
+}
+
+impl Barretenberg {
+    /// Counts the gates in the circuit with the serialized `bytecode`, reusing the cached count if there is one.
+    pub(crate) fn gate_count(&self, serialized_circuit: String) -> Result<u32, Error> {
+        let cached_artifacts = self.cached_artifacts(&serialized_circuit, false);
+        if let Some(gate_count) = cached_gate_count(cached_artifacts.as_ref()) {
+            return Ok(gate_count);
+        }
+
+        let (command, _temp_files) = self.prepare_gates(serialized_circuit)?;
+        let number_of_gates_needed = command.run(&self.config)?;
+
+        if let Some(artifacts) = cached_artifacts {
+            artifacts.put(Artifact::GateCount, &number_of_gates_needed.to_le_bytes());
+        }
+        Ok(number_of_gates_needed)
+    }
+
+    /// Prepares the command which counts the gates in the circuit with the serialized `bytecode`.
+    pub(crate) fn prepare_gates(
+        &self,
+        serialized_circuit: String,
+    ) -> Result<(GatesCommand, TempFiles), Error> {
+        let temp_directory = self.temp_directory()?;
+        let path_to_crs = self.crs_directory(&[])?;
+        let command = gates_command(
+            serialized_circuit,
+            path_to_crs,
+            self.streams_io()?,
+            temp_directory.path(),
+        )?;
+        Ok((command, TempFiles::new(temp_directory)))
+    }
+
+    /// Prepares the command which proves that `witness_values` satisfy `circuit`.
+    pub(crate) fn prepare_proof(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        witness_values: WitnessMap,
+        proving_key: &[u8],
+        is_recursive: bool,
+    ) -> Result<(ProveCommand, TempFiles), Error> {
+        // `bb prove` always constructs the proving key from the circuit, as barretenberg cannot load one.
+        if !proving_key.is_empty() {
+            trace::ignored_proving_key();
+        }
+
+        let temp_directory = self.temp_directory()?;
+        let secret_directory = self.secret_directory()?;
+        let path_to_crs = self.timed(ReportPhase::FileWrites, || {
+            self.crs_directory(common_reference_string)
+        })?;
+        let streams_io = self.streams_io()?;
+        let mut secret_files = Vec::new();
+
+        // Create a private temporary file for the witness, unless it can be streamed to barretenberg.
+        // The serialized witness is wiped from memory once it has been passed to barretenberg.
+        let serialized_witnesses: Zeroizing<Vec<u8>> =
+            Zeroizing::new(self.timed(ReportPhase::Serialization, || witness_values.try_into())?);
+        let witness = if streams_io {
+            InputFile::Stdin(serialized_witnesses)
+        } else {
+            let witness_path = secret_directory.path().join("witness").with_extension("tr");
+            let witness_file = self.timed(ReportPhase::FileWrites, || {
+                SecretFile::create(witness_path, &serialized_witnesses)
+            })?;
+            let witness = InputFile::Path(path_to_string(witness_file.path())?);
+            secret_files.push(witness_file);
+            witness
+        };
+
+        // Create a temporary file for the circuit
+        //
+        let circuit_path = temp_directory
+            .path()
+            .join("circuit")
+            .with_extension("bytecode");
+        let serialized_circuit =
+            self.timed(ReportPhase::Serialization, || serialize_circuit(circuit))?;
+        self.timed(ReportPhase::FileWrites, || {
+            write_to_file(serialized_circuit.as_bytes(), &circuit_path)
+        })?;
+
+        let proof = if streams_io {
+            OutputFile::Stdout
+        } else {
+            OutputFile::Path(path_to_string(
+                &temp_directory.path().join("proof").with_extension("proof"),
+            )?)
+        };
+
+        let command = ProveCommand {
+            verbose: self.config.verbose.unwrap_or(true),
+            path_to_crs,
+            is_recursive,
+            path_to_bytecode: path_to_string(&circuit_path)?,
+            witness,
+            proof,
+        };
+        let temp_files = TempFiles {
+            _secret_files: secret_files,
+            _secret_directory: Some(secret_directory),
+            _temp_directory: temp_directory,
+        };
+        Ok((command, temp_files))
+    }
+
+    /// Prepares the command which verifies `proof` against `verification_key`.
+    pub(crate) fn prepare_verification(
+        &self,
+        common_reference_string: &[u8],
+        proof: &[u8],
+        public_inputs: WitnessMap,
+        circuit: &Circuit,
+        verification_key: &[u8],
+        is_recursive: bool,
+    ) -> Result<(VerifyCommand, TempFiles), Error> {
+        let temp_directory = self.temp_directory()?;
+        let path_to_crs = self.crs_directory(common_reference_string)?;
 
         // Unlike when proving, we omit any unassigned witnesses.
         // Witness values should be ordered by their index but we skip over any indices without an assignment.
@@ -142,88 +300,119 @@ impl ProofSystemCompiler for Barretenberg {
         let proof_with_public_inputs =
             prepend_public_inputs(proof.to_vec(), flattened_public_inputs.to_vec());
 
-        // Create a temporary file for the proof
-        let proof_path = temp_directory.join("proof").with_extension("proof");
-        write_to_file(&proof_with_public_inputs, &proof_path);
-
-        // Create a temporary file for the circuit
-        let circuit_path = temp_directory.join("circuit").with_extension("bytecode");
-        let serialized_circuit = serialize_circuit(circuit);
-        write_to_file(serialized_circuit.as_bytes(), &circuit_path);
-
-        // Create the verification key and write it to the specified path
-        let vk_path = temp_directory.join("vk");
-        WriteVkCommand {
-            verbose: false,
-            path_to_crs: temp_dir_path.to_string(),
+        // Create a temporary file for the proof, unless it can be streamed to barretenberg
+        let proof = if self.streams_io()? {
+            InputFile::Stdin(proof_with_public_inputs.into())
+        } else {
+            let proof_path = temp_directory.path().join("proof").with_extension("proof");
+            write_to_file(&proof_with_public_inputs, &proof_path)?;
+            InputFile::Path(path_to_string(&proof_path)?)
+        };
+
+        let vk_path = self.write_verification_key(
+            temp_directory.path(),
+            &path_to_crs,
+            circuit,
+            verification_key,
             is_recursive,
-            path_to_bytecode: circuit_path.as_os_str().to_str().unwrap().to_string(),
-            path_to_vk_output: vk_path.as_os_str().to_str().unwrap().to_string(),
-        }
-        .run()
-        .expect("write vk command failed");
+            self.regenerate_verification_key,
+        )?;
 
-        // Verify the proof
-        Ok(VerifyCommand {
-            verbose: false,
-            path_to_crs: temp_dir_path.to_string(),
+        let command = VerifyCommand {
+            verbose: self.config.verbose.unwrap_or(false),
+            path_to_crs,
             is_recursive,
-            path_to_proof: proof_path.as_os_str().to_str().unwrap().to_string(),
-            path_to_vk: vk_path.as_os_str().to_str().unwrap().to_string(),
-        }
-        .run())
+            proof,
+            path_to_vk: path_to_string(&vk_path)?,
+        };
+        Ok((command, TempFiles::new(temp_directory)))
     }
+}
 
-    fn proof_as_fields(
-        &self,
-        _proof: &[u8],
-        _public_inputs: WitnessMap,
-    ) -> Result<Vec<FieldElement>, Self::Error> {
-        panic!("vk_as_fields not supported in this backend");
-    }
+/// Creates the command which counts the gates in the circuit with the serialized `bytecode`, using the CRS
+/// in `path_to_crs`. The circuit is streamed to barretenberg if `streams_io` is set, and is otherwise written
+/// to a file in `directory`, which must be kept alive until the command has run.
+pub(crate) fn gates_command(
+    serialized_circuit: String,
+    path_to_crs: String,
+    streams_io: bool,
+    directory: &Path,
+) -> Result<GatesCommand, Error> {
+    let bytecode = if streams_io {
+        InputFile::Stdin(serialized_circuit.into_bytes().into())
+    } else {
+        let circuit_path = directory.join("circuit").with_extension("bytecode");
+        write_to_file(serialized_circuit.as_bytes(), &circuit_path)?;
+        InputFile::Path(path_to_string(&circuit_path)?)
+    };
 
-    fn vk_as_fields(
-        &self,
-        _common_reference_string: &[u8],
-        _verification_key: &[u8],
-    ) -> Result<(Vec<FieldElement>, FieldElement), Self::Error> {
-        panic!("vk_as_fields not supported in this backend");
-    }
+    Ok(GatesCommand {
+        path_to_crs,
+        bytecode,
+    })
 }
 
-pub(super) fn write_to_file(bytes: &[u8], path: &Path) -> String {
-    let display = path.display();
+/// Returns the gate count stored in `cached_artifacts`, if there is one.
+pub(crate) fn cached_gate_count(cached_artifacts: Option<&CacheEntry>) -> Option<u32> {
+    let gate_count = cached_artifacts?.get(Artifact::GateCount)?;
+    <[u8; 4]>::try_from(gate_count.as_slice())
+        .ok()
+        .map(u32::from_le_bytes)
+}
 
-    let mut file = match File::create(path) {
-        Err(why) => panic!("couldn't create {display}: {why}"),
-        Ok(file) => file,
+pub(super) fn write_to_file(bytes: &[u8], path: &Path) -> Result<(), Error> {
+    let write_error = |source| Error::WriteFile {
+        path: path.to_path_buf(),
+        source,
     };
 
-    match file.write_all(bytes) {
-        Err(why) => panic!("couldn't write to {display}: {why}"),
-        Ok(_) => display.to_string(),
-    }
+    let mut file = File::create(path).map_err(write_error)?;
+    file.write_all(bytes).map_err(write_error)
 }
 
-pub(super) fn read_bytes_from_file(path: &str) -> std::io::Result<Vec<u8>> {
+pub(super) fn read_bytes_from_file(path: &Path) -> Result<Vec<u8>, Error> {
+    let read_error = |source| Error::ReadFile {
+        path: path.to_path_buf(),
+        source,
+    };
+
     // Open the file for reading.
-    let mut file = File::open(path)?;
+    let mut file = File::open(path).map_err(read_error)?;
 
     // Create a buffer to store the bytes.
     let mut buffer = Vec::new();
 
     // Read bytes from the file.
-    file.read_to_end(&mut buffer)?;
+    file.read_to_end(&mut buffer).map_err(read_error)?;
 
     Ok(buffer)
 }
 
+/// Converts `path` into a `String` so that it can be passed to the barretenberg binary.
+pub(super) fn path_to_string(path: &Path) -> Result<String, Error> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::NonUtf8Path(PathBuf::from(path)))
+}
+
 /// Removes the public inputs which are prepended to a proof by Barretenberg.
-fn remove_public_inputs(num_pub_inputs: usize, proof: &[u8]) -> Vec<u8> {
+pub(crate) fn remove_public_inputs(
+    num_pub_inputs: usize,
+    proof: &[u8],
+) -> Result<Vec<u8>, CliShimError> {
     // Barretenberg prepends the public inputs onto the proof so we need to remove
     // the first `num_pub_inputs` field elements.
     let num_bytes_to_remove = num_pub_inputs * FIELD_BYTES;
-    proof[num_bytes_to_remove..].to_vec()
+    match proof.get(num_bytes_to_remove..) {
+        Some(proof) => Ok(proof.to_vec()),
+        None => Err(CliShimError::MalformedOutput {
+            command: "prove",
+            reason: format!(
+                "proof of {} bytes is too short to contain {num_pub_inputs} public inputs",
+                proof.len()
+            ),
+        }),
+    }
 }
 
 /// Prepends a set of public inputs to a proof.
@@ -236,14 +425,158 @@ fn prepend_public_inputs(proof: Vec<u8>, public_inputs: Vec<FieldElement>) -> Ve
         .into_iter()
         .flat_map(|assignment| assignment.to_be_bytes());
 
-    public_inputs_bytes.chain(proof.into_iter()).collect()
+    public_inputs_bytes.chain(proof).collect()
 }
 
 // TODO: See nargo/src/artifacts/mod.rs
 // TODO: This method should live in ACVM and be the default method for serializing/deserializing circuits
-pub(super) fn serialize_circuit(circuit: &Circuit) -> String {
+pub(super) fn serialize_circuit(circuit: &Circuit) -> Result<String, Error> {
     use base64::Engine;
     let mut circuit_bytes: Vec<u8> = Vec::new();
-    circuit.write(&mut circuit_bytes).unwrap();
-    base64::engine::general_purpose::STANDARD.encode(circuit_bytes)
+    circuit
+        .write(&mut circuit_bytes)
+        .map_err(Error::CircuitSerialization)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(circuit_bytes))
+}
+
+#[test]
+fn verification_key_must_match_circuit() {
+    use std::collections::BTreeSet;
+
+    use acvm::acir::{
+        circuit::PublicInputs,
+        native_types::{Expression, Witness},
+    };
+
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let bb = Barretenberg::builder()
+        .crs_dir(temp_directory.path().join("crs"))
+        .build();
+
+    let expression = &(Witness(1) + Witness(2)) - &Expression::from(Witness(3));
+    let circuit = Circuit {
+        current_witness_index: 4,
+        opcodes: vec![Opcode::Arithmetic(expression)],
+        private_parameters: BTreeSet::from([Witness(1), Witness(2)]),
+        public_parameters: PublicInputs(BTreeSet::from([Witness(3)])),
+        return_values: PublicInputs::default(),
+    };
+
+    let verify = |verification_key: &[u8]| {
+        bb.verify_with_vk(
+            &[],
+            &[],
+            WitnessMap::new(),
+            &circuit,
+            verification_key,
+            false,
+        )
+        .map_err(|BackendError(error)| error)
+    };
+
+    assert!(matches!(verify(&[]), Err(Error::MissingVerificationKey)));
+    assert!(matches!(
+        verify(&[0; 4]),
+        Err(Error::MalformedVerificationKey(_))
+    ));
+
+    // A key for a circuit without any public inputs.
+    let mut verification_key = Vec::new();
+    verification_key.extend(2_u32.to_be_bytes());
+    verification_key.extend(4096_u32.to_be_bytes());
+    verification_key.extend(0_u32.to_be_bytes());
+    verification_key.extend(1_u32.to_be_bytes());
+    verification_key.extend(3_u32.to_be_bytes());
+    verification_key.extend(b"Q_M");
+    verification_key.extend([0; 64]);
+    verification_key.push(0);
+    verification_key.extend(0_u32.to_be_bytes());
+    assert!(matches!(
+        verify(&verification_key),
+        Err(Error::VerificationKeyMismatch {
+            expected: 1,
+            found: 0
+        })
+    ));
+
+    drop(temp_directory);
+}
+
+#[test]
+fn witnesses_are_streamed_to_the_binary() {
+    use std::collections::BTreeMap;
+
+    use acvm::acir::native_types::Witness;
+
+    // A binary which only accepts a witness streamed over stdin and echoes it back as the proof.
+    const STREAMING_BB_SCRIPT: &str = r#"#!/bin/sh
+[ "$1" = --version ] && echo 0.4.6 && exit 0
+command=$1
+while [ $# -gt 0 ]; do
+    case "$1" in
+        -b) bytecode=$2 ;;
+        -w) witness=$2 ;;
+        -o) output=$2 ;;
+    esac
+    shift
+done
+case "$command" in
+    gates) cat "$bytecode" > /dev/null && printf '\001\000\000\000\000\000\000\000' ;;
+    prove) [ "$witness" = /dev/stdin ] && [ "$output" = /dev/stdout ] && cat "$witness" ;;
+    *) exit 1 ;;
+esac
+"#;
+
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    crate::bb::write_mock_binary(&binary_path, STREAMING_BB_SCRIPT);
+
+    let bb = Barretenberg::builder()
+        .binary_path(&binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .artifact_cache(false)
+        .stream_io(true)
+        .build();
+
+    let witness_values =
+        WitnessMap::from(BTreeMap::from([(Witness(1), FieldElement::from(5_u128))]));
+    let serialized_witnesses: Vec<u8> = witness_values.clone().try_into().unwrap();
+
+    let proof = bb
+        .prove_with_pk(&[], &Circuit::default(), witness_values, &[], false)
+        .unwrap();
+    assert_eq!(proof, serialized_witnesses);
+
+    drop(temp_directory);
+}
+
+#[test]
+fn proving_keys_are_ignored() {
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    crate::bb::write_mock_binary(
+        &binary_path,
+        r#"#!/bin/sh
+case "$1" in
+    --version) echo 0.4.6 ;;
+    prove)
+        while [ "$1" != -o ]; do shift; done
+        printf proof > "$2"
+        ;;
+esac
+"#,
+    );
+
+    // Barretenberg cannot load a proving key, so proves as though none had been passed.
+    let bb = Barretenberg::builder()
+        .binary_path(&binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .artifact_cache(false)
+        .build();
+    let proof = bb
+        .prove_with_pk(&[], &Circuit::default(), WitnessMap::new(), &[1], false)
+        .unwrap();
+    assert_eq!(proof, b"proof");
+
+    drop(temp_directory);
 }
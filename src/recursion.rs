@@ -0,0 +1,346 @@
+use acvm::FieldElement;
+
+use crate::{Error, FIELD_BYTES};
+
+/// The number of bytes used to serialize a point on G1, being its `y` coordinate followed by its `x` coordinate.
+const G1_POINT_BYTES: usize = 2 * FIELD_BYTES;
+
+/// The number of bits held by the lower limb of a coordinate when it is split across two field elements.
+const LIMB_BITS: usize = 136;
+
+/// The number of field elements which a point on G1 is split into in the recursion format.
+#[cfg(test)]
+const FIELDS_PER_G1_POINT: usize = 4;
+
+/// The layout of an UltraPlonk proof, in the order in which its elements are serialized.
+///
+/// This matches the order in which the elements are added to the transcript and so the order in which
+/// barretenberg's recursive verifier expects them. See the `calldataload`s in `contract.sol` for the same layout.
+const PROOF_LAYOUT: &[ProofElement] = &[
+    // W_1, W_2, W_3, W_4, S, Z_PERM, Z_LOOKUP, T_1, T_2, T_3, T_4
+    ProofElement::G1Points(11),
+    // Evaluations of the wires, selectors, permutation and lookup polynomials.
+    ProofElement::Scalars(41),
+    // PI_Z, PI_Z_OMEGA
+    ProofElement::G1Points(2),
+];
+
+/// The number of bytes in an UltraPlonk proof, excluding any public inputs.
+pub(crate) const PROOF_BYTES: usize = proof_bytes();
+
+#[derive(Debug, Clone, Copy)]
+enum ProofElement {
+    G1Points(usize),
+    Scalars(usize),
+}
+
+const fn proof_bytes() -> usize {
+    let mut bytes = 0;
+    let mut index = 0;
+    while index < PROOF_LAYOUT.len() {
+        bytes += match PROOF_LAYOUT[index] {
+            ProofElement::G1Points(count) => count * G1_POINT_BYTES,
+            ProofElement::Scalars(count) => count * FIELD_BYTES,
+        };
+        index += 1;
+    }
+    bytes
+}
+
+/// Converts a proof and its public inputs into the field elements consumed by barretenberg's recursive verifier.
+///
+/// The public inputs come first, followed by the proof's elements in transcript order. Scalars are passed through
+/// as a single field element, whereas each coordinate of a point on G1 is split into a 136-bit low limb and
+/// a high limb, as the base field of BN254 does not fit within its scalar field. Points are therefore represented
+/// as `[x_lo, x_hi, y_lo, y_hi]`.
+pub(crate) fn proof_as_fields(
+    proof: &[u8],
+    public_inputs: impl IntoIterator<Item = FieldElement>,
+) -> Result<Vec<FieldElement>, Error> {
+    if proof.len() != PROOF_BYTES {
+        return Err(Error::MalformedProof(proof.len()));
+    }
+
+    let mut fields: Vec<FieldElement> = public_inputs.into_iter().collect();
+    let mut remaining = proof;
+    for element in PROOF_LAYOUT {
+        match *element {
+            ProofElement::G1Points(count) => {
+                let (points, rest) = remaining.split_at(count * G1_POINT_BYTES);
+                for point in points.chunks_exact(G1_POINT_BYTES) {
+                    let (y, x) = point.split_at(FIELD_BYTES);
+                    fields.extend(split_coordinate(x));
+                    fields.extend(split_coordinate(y));
+                }
+                remaining = rest;
+            }
+            ProofElement::Scalars(count) => {
+                let (scalars, rest) = remaining.split_at(count * FIELD_BYTES);
+                fields.extend(
+                    scalars
+                        .chunks_exact(FIELD_BYTES)
+                        .map(FieldElement::from_be_bytes_reduce),
+                );
+                remaining = rest;
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+/// Splits a big-endian encoded coordinate into its low and high limbs.
+fn split_coordinate(coordinate: &[u8]) -> [FieldElement; 2] {
+    let (high, low) = coordinate.split_at(FIELD_BYTES - LIMB_BITS / 8);
+    [
+        FieldElement::from_be_bytes_reduce(low),
+        FieldElement::from_be_bytes_reduce(high),
+    ]
+}
+
+/// The properties of the circuit described by a verification key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct VerificationKeyInfo {
+    pub(crate) circuit_size: u32,
+    pub(crate) num_public_inputs: u32,
+}
+
+/// Checks that `verification_key` has the structure of a verification key written by barretenberg.
+///
+/// Barretenberg does not validate the keys which it reads, so a malformed key must be rejected before it is passed on.
+/// The key is serialized big-endian as the circuit type, circuit size and number of public inputs as `u32`s,
+/// followed by a map of named commitments, a flag for whether the circuit contains a recursive proof and
+/// the indices of that proof's public inputs.
+pub(crate) fn check_verification_key(
+    verification_key: &[u8],
+) -> Result<VerificationKeyInfo, Error> {
+    let mut reader = VerificationKeyReader(verification_key);
+
+    let _circuit_type = reader.read_u32("circuit type")?;
+    let circuit_size = reader.read_u32("circuit size")?;
+    if !circuit_size.is_power_of_two() {
+        return Err(Error::MalformedVerificationKey(format!(
+            "circuit size {circuit_size} is not a power of two"
+        )));
+    }
+    let num_public_inputs = reader.read_u32("number of public inputs")?;
+
+    let num_commitments = reader.read_u32("number of commitments")?;
+    if num_commitments == 0 {
+        return Err(Error::MalformedVerificationKey(
+            "verification key contains no commitments".to_string(),
+        ));
+    }
+    for _ in 0..num_commitments {
+        let name_length = reader.read_u32("commitment name length")? as usize;
+        let name = reader.read_bytes(name_length, "commitment name")?;
+        if std::str::from_utf8(name).is_err() {
+            return Err(Error::MalformedVerificationKey(
+                "commitment name is not valid UTF-8".to_string(),
+            ));
+        }
+        reader.read_bytes(G1_POINT_BYTES, "commitment")?;
+    }
+
+    let contains_recursive_proof = reader.read_bytes(1, "recursive proof flag")?[0];
+    if contains_recursive_proof > 1 {
+        return Err(Error::MalformedVerificationKey(format!(
+            "recursive proof flag {contains_recursive_proof} is not a boolean"
+        )));
+    }
+    let num_indices = reader.read_u32("number of recursive proof public inputs")? as usize;
+    for _ in 0..num_indices {
+        reader.read_u32("recursive proof public input index")?;
+    }
+
+    if !reader.0.is_empty() {
+        return Err(Error::MalformedVerificationKey(format!(
+            "{} unexpected trailing bytes",
+            reader.0.len()
+        )));
+    }
+    Ok(VerificationKeyInfo {
+        circuit_size,
+        num_public_inputs,
+    })
+}
+
+/// Reads the big-endian encoded fields of a verification key.
+struct VerificationKeyReader<'a>(&'a [u8]);
+
+impl<'a> VerificationKeyReader<'a> {
+    fn read_bytes(&mut self, length: usize, field: &str) -> Result<&'a [u8], Error> {
+        if self.0.len() < length {
+            return Err(Error::MalformedVerificationKey(format!(
+                "verification key ends before its {field}"
+            )));
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self, field: &str) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4, field)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Reassembles a proof from the fields returned by [`proof_as_fields`], for checking the conversion in tests.
+#[cfg(test)]
+fn proof_from_fields(num_public_inputs: usize, fields: &[FieldElement]) -> Vec<u8> {
+    let coordinate = |limbs: &[FieldElement]| {
+        let low = limbs[0].to_be_bytes();
+        let high = limbs[1].to_be_bytes();
+        let mut bytes = high[LIMB_BITS / 8..].to_vec();
+        bytes.extend_from_slice(&low[FIELD_BYTES - LIMB_BITS / 8..]);
+        bytes
+    };
+
+    let mut proof = Vec::new();
+    let mut remaining = &fields[num_public_inputs..];
+    for element in PROOF_LAYOUT {
+        match *element {
+            ProofElement::G1Points(count) => {
+                let (points, rest) = remaining.split_at(count * FIELDS_PER_G1_POINT);
+                for point in points.chunks_exact(FIELDS_PER_G1_POINT) {
+                    proof.extend(coordinate(&point[2..]));
+                    proof.extend(coordinate(&point[..2]));
+                }
+                remaining = rest;
+            }
+            ProofElement::Scalars(count) => {
+                let (scalars, rest) = remaining.split_at(count);
+                proof.extend(scalars.iter().flat_map(|scalar| scalar.to_be_bytes()));
+                remaining = rest;
+            }
+        }
+    }
+    proof
+}
+
+#[test]
+fn proof_layout_matches_verifier_contract() {
+    // The verifier contract reads the final element of the proof from offset 0x840.
+    assert_eq!(PROOF_BYTES, 0x840 + FIELD_BYTES);
+
+    let num_fields: usize = PROOF_LAYOUT
+        .iter()
+        .map(|element| match *element {
+            ProofElement::G1Points(count) => count * FIELDS_PER_G1_POINT,
+            ProofElement::Scalars(count) => count,
+        })
+        .sum();
+    assert_eq!(num_fields, 93);
+}
+
+#[test]
+fn proof_fields_round_trip() {
+    // Coordinates must be below the BN254 base field modulus, so leave their top bits clear.
+    let proof: Vec<u8> = (0..PROOF_BYTES)
+        .map(|index| {
+            if index % FIELD_BYTES == 0 {
+                0
+            } else {
+                index as u8
+            }
+        })
+        .collect();
+    let public_inputs = vec![FieldElement::from(3_u128), FieldElement::from(7_u128)];
+
+    let fields = proof_as_fields(&proof, public_inputs.clone()).unwrap();
+    assert_eq!(fields.len(), public_inputs.len() + 93);
+    assert_eq!(fields[..2], public_inputs);
+
+    // The first point is serialized as `y || x` but converted to `[x_lo, x_hi, y_lo, y_hi]`.
+    let x = &proof[FIELD_BYTES..G1_POINT_BYTES];
+    assert_eq!(fields[2], FieldElement::from_be_bytes_reduce(&x[15..]));
+    assert_eq!(fields[3], FieldElement::from_be_bytes_reduce(&x[..15]));
+
+    assert_eq!(proof_from_fields(public_inputs.len(), &fields), proof);
+
+    assert!(matches!(
+        proof_as_fields(&proof[1..], Vec::new()),
+        Err(Error::MalformedProof(_))
+    ));
+}
+
+#[test]
+fn malformed_verification_keys_are_rejected() {
+    let mut verification_key = Vec::new();
+    verification_key.extend(2_u32.to_be_bytes()); // circuit type
+    verification_key.extend(4096_u32.to_be_bytes()); // circuit size
+    verification_key.extend(1_u32.to_be_bytes()); // number of public inputs
+    verification_key.extend(1_u32.to_be_bytes()); // number of commitments
+    verification_key.extend(3_u32.to_be_bytes());
+    verification_key.extend(b"Q_M");
+    verification_key.extend([0; G1_POINT_BYTES]);
+    verification_key.push(0); // contains recursive proof
+    verification_key.extend(0_u32.to_be_bytes()); // recursive proof public inputs
+    assert_eq!(
+        check_verification_key(&verification_key).unwrap(),
+        VerificationKeyInfo {
+            circuit_size: 4096,
+            num_public_inputs: 1
+        }
+    );
+
+    let truncated = &verification_key[..verification_key.len() - 1];
+    let mut trailing = verification_key.clone();
+    trailing.push(0);
+    let mut bad_circuit_size = verification_key.clone();
+    bad_circuit_size[4..8].copy_from_slice(&4095_u32.to_be_bytes());
+
+    for malformed_key in [&[][..], truncated, &trailing, &bad_circuit_size] {
+        assert!(matches!(
+            check_verification_key(malformed_key),
+            Err(Error::MalformedVerificationKey(_))
+        ));
+    }
+}
+
+#[test]
+fn recursive_proof_round_trips() {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use acvm::{
+        acir::{
+            circuit::{Circuit, Opcode, PublicInputs},
+            native_types::{Expression, Witness, WitnessMap},
+        },
+        ProofSystemCompiler,
+    };
+
+    use crate::Barretenberg;
+
+    let expression = &(Witness(1) + Witness(2)) - &Expression::from(Witness(3));
+    let circuit = Circuit {
+        current_witness_index: 4,
+        opcodes: vec![Opcode::Arithmetic(expression)],
+        private_parameters: BTreeSet::from([Witness(1), Witness(2)]),
+        public_parameters: PublicInputs(BTreeSet::from([Witness(3)])),
+        return_values: PublicInputs::default(),
+    };
+    let witness_values = WitnessMap::from(BTreeMap::from([
+        (Witness(1), FieldElement::from(1_u128)),
+        (Witness(2), FieldElement::from(2_u128)),
+        (Witness(3), FieldElement::from(3_u128)),
+    ]));
+    let public_inputs =
+        WitnessMap::from(BTreeMap::from([(Witness(3), FieldElement::from(3_u128))]));
+
+    let bb = Barretenberg::builder()
+        .regenerate_verification_key(true)
+        .build();
+    let proof = bb
+        .prove_with_pk(&[], &circuit, witness_values, &[], true)
+        .unwrap();
+
+    let fields = bb.proof_as_fields(&proof, public_inputs.clone()).unwrap();
+    assert_eq!(fields[0], FieldElement::from(3_u128));
+    assert_eq!(proof_from_fields(1, &fields), proof);
+
+    assert!(bb
+        .verify_with_vk(&[], &proof, public_inputs, &circuit, &[], true)
+        .unwrap());
+}
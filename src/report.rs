@@ -0,0 +1,262 @@
+//! Reports of where the time and resources spent creating proofs and keys went, for benchmarking circuits.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use acvm::acir::{circuit::Circuit, native_types::WitnessMap};
+use acvm::ProofSystemCompiler;
+use serde::{Serialize, Serializer};
+
+use crate::{bb::ResourceUsage, BackendError, Barretenberg, Operation};
+
+/// A phase of the work done by the backend, whose wall time is recorded in a [`PerformanceReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPhase {
+    /// Counting the gates in the circuit.
+    GateCount,
+    /// Serializing the circuit and witness.
+    Serialization,
+    /// Writing the inputs to barretenberg, including the CRS, to temporary files.
+    FileWrites,
+    /// Running barretenberg, including reading the output which it writes.
+    Subprocess,
+    /// Reading the keys which barretenberg has written.
+    FileReads,
+    /// Converting barretenberg's output into the format returned by the backend, such as removing public inputs from proofs.
+    PostProcessing,
+}
+
+/// The total wall time spent in a single phase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PhaseTiming {
+    pub phase: ReportPhase,
+    #[serde(rename = "wall_time_ms", serialize_with = "milliseconds")]
+    pub wall_time: Duration,
+}
+
+/// The running time and resource usage of a single barretenberg process.
+///
+/// Resource usage is only available for commands which were run in a process of their own,
+/// rather than by a worker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandUsage {
+    pub operation: Operation,
+    #[serde(rename = "wall_time_ms", serialize_with = "milliseconds")]
+    pub wall_time: Duration,
+    /// The peak resident set size of the process, including any processes which it started.
+    pub peak_rss_bytes: Option<u64>,
+    #[serde(rename = "user_cpu_time_ms", serialize_with = "optional_milliseconds")]
+    pub user_cpu_time: Option<Duration>,
+    #[serde(
+        rename = "system_cpu_time_ms",
+        serialize_with = "optional_milliseconds"
+    )]
+    pub system_cpu_time: Option<Duration>,
+}
+
+/// A report of where the time spent on a single call to the backend went.
+///
+/// Durations are serialized as fractional milliseconds, with fields suffixed `_ms`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PerformanceReport {
+    /// The number of gates in the circuit.
+    pub gate_count: Option<u32>,
+    /// The wall time of the whole call.
+    #[serde(rename = "wall_time_ms", serialize_with = "milliseconds")]
+    pub wall_time: Duration,
+    /// The wall time of each phase, in the order in which the phases were first entered.
+    pub phases: Vec<PhaseTiming>,
+    /// Each barretenberg command which was run, in the order in which they completed.
+    pub commands: Vec<CommandUsage>,
+}
+
+/// Collects a [`PerformanceReport`] while it is shared between clones of the configuration.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Recorder(Arc<Mutex<PerformanceReport>>);
+
+impl Recorder {
+    /// Adds `wall_time` to the time spent in `phase`.
+    fn record_phase(&self, phase: ReportPhase, wall_time: Duration) {
+        let mut report = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        match report
+            .phases
+            .iter_mut()
+            .find(|timing| timing.phase == phase)
+        {
+            Some(timing) => timing.wall_time += wall_time,
+            None => report.phases.push(PhaseTiming { phase, wall_time }),
+        }
+    }
+
+    /// Adds a barretenberg command which performed `operation` to the report.
+    pub(crate) fn record_command(
+        &self,
+        operation: Operation,
+        wall_time: Duration,
+        usage: Option<ResourceUsage>,
+    ) {
+        let mut report = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        report.commands.push(CommandUsage {
+            operation,
+            wall_time,
+            peak_rss_bytes: usage.map(|usage| usage.peak_rss_bytes),
+            user_cpu_time: usage.map(|usage| usage.user_cpu_time),
+            system_cpu_time: usage.map(|usage| usage.system_cpu_time),
+        });
+    }
+
+    fn finish(self, gate_count: Option<u32>, wall_time: Duration) -> PerformanceReport {
+        let mut report = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        PerformanceReport {
+            gate_count,
+            wall_time,
+            ..std::mem::take(&mut *report)
+        }
+    }
+}
+
+fn milliseconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+fn optional_milliseconds<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => milliseconds(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl Barretenberg {
+    /// Creates a proof as [`ProofSystemCompiler::prove_with_pk`] does, along with a report of where the time went.
+    ///
+    /// The circuit's gates are counted first, so that the report includes its gate count.
+    pub fn prove_with_report(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        witness_values: WitnessMap,
+        proving_key: &[u8],
+        is_recursive: bool,
+    ) -> Result<(Vec<u8>, PerformanceReport), BackendError> {
+        let (backend, recorder) = self.recording();
+        let started = Instant::now();
+
+        let gate_count = backend.timed(ReportPhase::GateCount, || {
+            backend.get_exact_circuit_size(circuit)
+        })?;
+        let proof = backend.prove_with_pk(
+            common_reference_string,
+            circuit,
+            witness_values,
+            proving_key,
+            is_recursive,
+        )?;
+        Ok((proof, recorder.finish(Some(gate_count), started.elapsed())))
+    }
+
+    /// Constructs the proving and verification keys as [`Barretenberg::preprocess`] does,
+    /// along with a report of where the time went.
+    pub fn preprocess_with_report(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        is_recursive: bool,
+    ) -> Result<(Vec<u8>, Vec<u8>, PerformanceReport), BackendError> {
+        let (backend, recorder) = self.recording();
+        let started = Instant::now();
+
+        let gate_count = backend.timed(ReportPhase::GateCount, || {
+            backend.get_exact_circuit_size(circuit)
+        })?;
+        let (proving_key, verification_key) =
+            backend.preprocess(common_reference_string, circuit, is_recursive)?;
+        let report = recorder.finish(Some(gate_count), started.elapsed());
+        Ok((proving_key, verification_key, report))
+    }
+
+    /// Returns a copy of this backend which records a report into the returned recorder.
+    fn recording(&self) -> (Barretenberg, Recorder) {
+        let recorder = Recorder::default();
+        let mut backend = self.clone();
+        backend.config.recorder = Some(recorder.clone());
+        (backend, recorder)
+    }
+
+    /// Runs `f`, adding its wall time to `phase` if a report is being recorded.
+    pub(crate) fn timed<T>(&self, phase: ReportPhase, f: impl FnOnce() -> T) -> T {
+        let Some(recorder) = &self.config.recorder else {
+            return f();
+        };
+        let started = Instant::now();
+        let result = f();
+        recorder.record_phase(phase, started.elapsed());
+        result
+    }
+}
+
+#[test]
+fn reports_are_recorded_for_proofs() {
+    use tempfile::tempdir;
+
+    let temp_directory = tempdir().expect("could not create a temporary directory");
+    let binary_path = temp_directory.path().join("bb");
+    crate::bb::write_mock_binary(
+        &binary_path,
+        r#"#!/bin/sh
+case "$1" in
+    --version) echo 0.4.6 ;;
+    gates) printf '\007\000\000\000\000\000\000\000' ;;
+    prove)
+        while [ "$1" != -o ]; do shift; done
+        printf proof > "$2"
+        ;;
+esac
+"#,
+    );
+
+    let backend = Barretenberg::builder()
+        .binary_path(binary_path)
+        .crs_dir(temp_directory.path().join("crs"))
+        .artifact_cache(false)
+        .build();
+    let (proof, report) = backend
+        .prove_with_report(&[], &Circuit::default(), WitnessMap::new(), &[], false)
+        .unwrap();
+    assert_eq!(proof, b"proof");
+
+    assert_eq!(report.gate_count, Some(7));
+    let phases: Vec<ReportPhase> = report.phases.iter().map(|timing| timing.phase).collect();
+    for phase in [
+        ReportPhase::GateCount,
+        ReportPhase::Serialization,
+        ReportPhase::FileWrites,
+        ReportPhase::Subprocess,
+        ReportPhase::PostProcessing,
+    ] {
+        assert!(phases.contains(&phase), "{phase:?} was not recorded");
+    }
+    let operations: Vec<Operation> = report
+        .commands
+        .iter()
+        .map(|command| command.operation)
+        .collect();
+    assert_eq!(operations, [Operation::GateCount, Operation::Prove]);
+    assert!(report
+        .commands
+        .iter()
+        .all(|command| command.peak_rss_bytes.is_some() && command.user_cpu_time.is_some()));
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["gate_count"], 7);
+    assert_eq!(json["commands"][1]["operation"], "prove");
+    assert!(json["commands"][1]["peak_rss_bytes"].as_u64().unwrap() > 0);
+    assert!(json["phases"][0]["wall_time_ms"].is_f64());
+
+    drop(temp_directory);
+}
@@ -0,0 +1,89 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use crate::Error;
+
+/// A file holding secret data, such as a witness, which is passed to barretenberg.
+///
+/// The file may only be read by the current user and is overwritten with zeros before it is removed,
+/// which happens when it is dropped.
+#[derive(Debug)]
+pub(crate) struct SecretFile {
+    path: PathBuf,
+}
+
+impl SecretFile {
+    /// Creates a new file at `path` holding `contents`.
+    pub(crate) fn create(path: PathBuf, contents: &[u8]) -> Result<SecretFile, Error> {
+        let write_error = |source| Error::WriteFile {
+            path: path.clone(),
+            source,
+        };
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .map_err(write_error)?;
+
+        // Once the file exists it must be wiped, even if it could not be completely written.
+        let secret_file = SecretFile { path: path.clone() };
+        file.write_all(contents).map_err(write_error)?;
+        Ok(secret_file)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Overwrites the contents of the file with zeros, so that they cannot be recovered once it is removed.
+    fn wipe(&self) -> std::io::Result<()> {
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        let length = file.metadata()?.len();
+
+        let zeros = [0; 4096];
+        let mut remaining = length;
+        while remaining > 0 {
+            let chunk = remaining.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        file.sync_all()
+    }
+}
+
+impl Drop for SecretFile {
+    fn drop(&mut self) {
+        // The file is removed even if it could not be wiped, as leaving it behind would be worse.
+        let _ = self.wipe();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[test]
+fn secret_files_are_private_and_wiped() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_directory = tempfile::tempdir().expect("could not create a temporary directory");
+    let path = temp_directory.path().join("witness");
+
+    let secret_file = SecretFile::create(path.clone(), b"secret witness").unwrap();
+    let permissions = std::fs::metadata(secret_file.path()).unwrap().permissions();
+    assert_eq!(permissions.mode() & 0o777, 0o600);
+    assert_eq!(std::fs::read(&path).unwrap(), b"secret witness");
+
+    // A second link to the file lets us observe its contents after it has been removed.
+    let link_path = temp_directory.path().join("link");
+    std::fs::hard_link(&path, &link_path).unwrap();
+    drop(secret_file);
+
+    assert!(!path.exists());
+    assert_eq!(std::fs::read(&link_path).unwrap(), [0; 14]);
+
+    drop(temp_directory);
+}
@@ -1,11 +1,10 @@
-use super::proof_system::{serialize_circuit, write_to_file};
 use crate::{
-    bb::{ContractCommand, WriteVkCommand},
-    proof_system::read_bytes_from_file,
-    BackendError, Barretenberg,
+    artifacts::{Artifact, CacheEntry},
+    bb::{ContractCommand, OutputFile},
+    proof_system::{path_to_string, read_bytes_from_file, serialize_circuit, TempFiles},
+    trace, BackendError, Barretenberg, Error,
 };
 use acvm::{acir::circuit::Circuit, SmartContract};
-use tempfile::tempdir;
 
 /// Embed the Solidity verifier file
 const ULTRA_VERIFIER_CONTRACT: &str = include_str!("contract.sol");
@@ -15,55 +14,111 @@ impl SmartContract for Barretenberg {
 
     fn eth_contract_from_vk(
         &self,
-        _common_reference_string: &[u8],
+        common_reference_string: &[u8],
         circuit: &Circuit,
-        _verification_key: &[u8],
+        verification_key: &[u8],
     ) -> Result<String, Self::Error> {
-        let temp_directory = tempdir().expect("could not create a temporary directory");
-        let temp_directory_path = temp_directory.path();
-        let temp_dir_path = temp_directory_path.to_str().unwrap();
-
-        // Create a temporary file for the circuit
-        let circuit_path = temp_directory_path
-            .join("circuit")
-            .with_extension("bytecode");
-        let serialized_circuit = serialize_circuit(circuit);
-        write_to_file(serialized_circuit.as_bytes(), &circuit_path);
-
-        // Create the verification key and write it to the specified path
-        let vk_path = temp_directory_path.join("vk").to_str().unwrap().to_string();
-        WriteVkCommand {
-            verbose: false,
-            path_to_crs: temp_dir_path.to_string(),
-            is_recursive: false,
-            path_to_bytecode: circuit_path.as_os_str().to_str().unwrap().to_string(),
-            path_to_vk_output: vk_path.clone(),
+        let _circuit_span = trace::enter_circuit(circuit);
+        match self.prepare_contract(common_reference_string, circuit, verification_key)? {
+            ContractSource::Cached(contract) => Ok(contract),
+            ContractSource::Generate {
+                command,
+                cached_artifacts,
+                temp_files,
+            } => {
+                let verification_key_library_bytes = command.run(&self.config)?;
+                drop(temp_files);
+                Ok(finish_contract(
+                    verification_key_library_bytes,
+                    cached_artifacts,
+                )?)
+            }
         }
-        .run()
-        .expect("write vk command failed");
-
-        let path_to_contract = temp_directory_path
-            .join("contract")
-            .to_str()
-            .unwrap()
-            .to_string();
-        ContractCommand {
-            verbose: false,
-            path_to_crs: temp_dir_path.to_string(),
-            path_to_vk: vk_path,
-            path_to_contract: path_to_contract.clone(),
+    }
+}
+
+/// How the contract for a verification key is obtained.
+pub(crate) enum ContractSource {
+    /// The contract was found in the artifact cache.
+    Cached(String),
+    /// The contract must be generated by running `command`.
+    Generate {
+        command: ContractCommand,
+        cached_artifacts: Option<CacheEntry>,
+        temp_files: TempFiles,
+    },
+}
+
+impl Barretenberg {
+    /// Looks up the contract for `verification_key` in the artifact cache,
+    /// otherwise preparing the command which generates it.
+    pub(crate) fn prepare_contract(
+        &self,
+        common_reference_string: &[u8],
+        circuit: &Circuit,
+        verification_key: &[u8],
+    ) -> Result<ContractSource, Error> {
+        let temp_directory = self.temp_directory()?;
+        let temp_directory_path = temp_directory.path();
+        let path_to_crs = self.crs_directory(common_reference_string)?;
+
+        // The contract only accepts proofs for `circuit`, so its key can always be regenerated from the circuit.
+        let vk_path = self.write_verification_key(
+            temp_directory_path,
+            &path_to_crs,
+            circuit,
+            verification_key,
+            false,
+            true,
+        )?;
+
+        // The cached contract may only be reused if it was generated from the same verification key.
+        let serialized_circuit = serialize_circuit(circuit)?;
+        let verification_key = read_bytes_from_file(&vk_path)?;
+        let cached_artifacts =
+            self.cached_artifacts(&serialized_circuit, false)
+                .filter(|artifacts| {
+                    artifacts.get(Artifact::VerificationKey).as_ref() == Some(&verification_key)
+                });
+        if let Some(contract) = cached_artifacts
+            .as_ref()
+            .and_then(|artifacts| artifacts.get(Artifact::Contract))
+            .and_then(|contract| String::from_utf8(contract).ok())
+        {
+            return Ok(ContractSource::Cached(contract));
         }
-        .run()
-        .expect("contract command failed");
 
-        let verification_key_library_bytes = read_bytes_from_file(&path_to_contract).unwrap();
-        let verification_key_library = String::from_utf8(verification_key_library_bytes).unwrap();
+        let contract = if self.streams_io()? {
+            OutputFile::Stdout
+        } else {
+            OutputFile::Path(path_to_string(&temp_directory_path.join("contract"))?)
+        };
+        let command = ContractCommand {
+            verbose: self.config.verbose.unwrap_or(false),
+            path_to_crs,
+            path_to_vk: path_to_string(&vk_path)?,
+            contract,
+        };
+        Ok(ContractSource::Generate {
+            command,
+            cached_artifacts,
+            temp_files: TempFiles::new(temp_directory),
+        })
+    }
+}
+
+/// Completes the verification key library generated by barretenberg with the verifier, caching the resulting contract.
+pub(crate) fn finish_contract(
+    verification_key_library_bytes: Vec<u8>,
+    cached_artifacts: Option<CacheEntry>,
+) -> Result<String, Error> {
+    let verification_key_library = String::from_utf8(verification_key_library_bytes)?;
 
-        drop(temp_directory);
-        Ok(format!(
-            "{verification_key_library}{ULTRA_VERIFIER_CONTRACT}"
-        ))
+    let contract = format!("{verification_key_library}{ULTRA_VERIFIER_CONTRACT}");
+    if let Some(artifacts) = cached_artifacts {
+        artifacts.put(Artifact::Contract, contract.as_bytes());
     }
+    Ok(contract)
 }
 
 #[cfg(test)]
@@ -93,7 +148,7 @@ mod tests {
             return_values: PublicInputs::default(),
         };
 
-        let bb = Barretenberg;
+        let bb = Barretenberg::new();
 
         let common_reference_string = Vec::new();
         let verification_key = Vec::new();
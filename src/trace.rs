@@ -0,0 +1,205 @@
+//! Reporting of barretenberg's progress through `tracing`, which is enabled by the `tracing` feature.
+//!
+//! Without the feature these do nothing, so that their callers need not be conditionally compiled.
+
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::{process::ExitStatus, time::Duration};
+
+use acvm::acir::circuit::Circuit;
+
+use crate::bb::ProgressPhase;
+
+/// A span identifying the circuit which barretenberg is operating on, which is exited when dropped.
+pub(crate) struct CircuitSpan {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+/// Enters a span identifying `circuit` by a hash of its bytecode, within which barretenberg's output is logged.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn enter_circuit(circuit: &Circuit) -> CircuitSpan {
+    CircuitSpan {
+        #[cfg(feature = "tracing")]
+        _span: circuit_span(circuit).entered(),
+    }
+}
+
+/// Instruments `future` with a span identifying `circuit`, as [`enter_circuit`] does for blocking operations.
+#[cfg(feature = "async")]
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn in_circuit<F: Future>(
+    circuit: &Circuit,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    return tracing::Instrument::instrument(future, circuit_span(circuit));
+    #[cfg(not(feature = "tracing"))]
+    return future;
+}
+
+#[cfg(feature = "tracing")]
+fn circuit_span(circuit: &Circuit) -> tracing::Span {
+    use sha2::{Digest, Sha256};
+
+    // The hash only identifies the circuit in logs, so a circuit which cannot be serialized is left unidentified.
+    let mut bytecode = Vec::new();
+    let hash: String = match circuit.write(&mut bytecode) {
+        Ok(()) => Sha256::digest(&bytecode)[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+        Err(_) => String::new(),
+    };
+    tracing::info_span!("circuit", circuit = %hash)
+}
+
+/// Logs each line which barretenberg writes to one of its output streams as a `tracing` event,
+/// along with the operation being performed and the phase which barretenberg has reached.
+pub(crate) struct OutputLog {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    operation: &'static str,
+    #[cfg(feature = "tracing")]
+    stream: &'static str,
+}
+
+impl OutputLog {
+    /// Creates a log of the `stream` written by the `operation` command, whose events are recorded within the current span.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(operation: &'static str, stream: &'static str) -> OutputLog {
+        OutputLog {
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+            #[cfg(feature = "tracing")]
+            operation,
+            #[cfg(feature = "tracing")]
+            stream,
+        }
+    }
+
+    /// Logs a single `line` of output, which was written once barretenberg reached `phase`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn line(&self, line: &str, phase: Option<ProgressPhase>) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            parent: &self.span,
+            operation = self.operation,
+            stream = self.stream,
+            phase = ?phase,
+            "{line}"
+        );
+    }
+}
+
+/// Warns that a proving key was passed to barretenberg, which cannot load one so constructs the key again instead.
+pub(crate) fn ignored_proving_key() {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        "barretenberg {} cannot load a proving key, so it is constructed again from the circuit",
+        crate::bb::VERSION
+    );
+}
+
+/// Records the exit status and running time of the barretenberg command whose span is current.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_exit(status: &ExitStatus, duration: Duration) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("exit_status", tracing::field::display(status));
+        span.record(
+            "duration_ms",
+            u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+        );
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn output_lines_are_logged_with_their_phase() {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// The fields of an event, formatted as strings.
+    #[derive(Default)]
+    struct Fields(Vec<(String, String)>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    /// Collects the fields of every event.
+    #[derive(Default)]
+    struct Events(Arc<Mutex<Vec<Fields>>>);
+
+    impl Subscriber for Events {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields);
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let events = Events::default();
+    let recorded = events.0.clone();
+    tracing::subscriber::with_default(events, || {
+        let log = OutputLog::new("prove", "stderr");
+        log.line(
+            "building circuit...",
+            Some(ProgressPhase::CircuitConstruction),
+        );
+        log.line("gates: 2775", Some(ProgressPhase::CircuitConstruction));
+        log.line("creating proof...", Some(ProgressPhase::Proof));
+    });
+
+    let recorded = recorded.lock().unwrap();
+    let field = |event: &Fields, name: &str| {
+        event
+            .0
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    };
+    let logged: Vec<_> = recorded
+        .iter()
+        .map(|event| (field(event, "message"), field(event, "phase")))
+        .collect();
+    assert_eq!(
+        logged,
+        [
+            (
+                "building circuit...".into(),
+                "Some(CircuitConstruction)".into()
+            ),
+            ("gates: 2775".into(), "Some(CircuitConstruction)".into()),
+            ("creating proof...".into(), "Some(Proof)".into()),
+        ]
+    );
+    assert!(recorded
+        .iter()
+        .all(|event| field(event, "operation") == "prove" && field(event, "stream") == "stderr"));
+}